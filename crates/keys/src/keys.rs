@@ -1,26 +1,280 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
+
+/// Names and codes of a [`Key`], one row of the key table
+#[derive(Debug, PartialEq, Eq)]
+pub struct KeyInfo {
+    /// Canonical name first, then the aliases
+    pub names: &'static [&'static str],
+    /// Kanata key name, `None` for keys kanata only has as actions and for
+    /// keys kanata doesn't know
+    pub kanata: Option<&'static str>,
+    /// QMK keycode name
+    pub qmk: &'static str,
+    /// HID usage page and usage id
+    pub hid: Option<(u16, u16)>,
+    /// Linux input event code
+    pub evdev: Option<u16>,
+}
+
+macro_rules! opt {
+    (_) => {
+        None
+    };
+    ($value:expr) => {
+        Some($value)
+    };
+}
+
+/// Declares [`Key`] with its names and codes from the rows
+/// `Variant [names] kanata qmk (hid page, id) evdev;`, `_` marks a missing one
+macro_rules! keys {
+    ($($key:ident [$($name:literal),+] $kanata:tt $qmk:literal $hid:tt $evdev:tt;)*) => {
+        #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+        pub enum Key {
+            /// Virtual `fnN` key, has no code of its own
+            Fn(u8),
+            $($key,)*
+        }
+
+        impl Key {
+            /// Every key except `Fn`
+            pub const ALL: &[Key] = &[$(Key::$key),*];
+
+            /// Every name accepted by [`Key::from_str`] except `fnN`
+            pub const NAMES: &[&str] = &[$($($name),+),*];
+
+            /// Row of the key table, `None` for `Fn`
+            pub fn info(&self) -> Option<&'static KeyInfo> {
+                match self {
+                    Key::Fn(_) => None,
+                    $(Key::$key => {
+                        const INFO: KeyInfo = KeyInfo {
+                            names: &[$($name),+],
+                            kanata: opt!($kanata),
+                            qmk: $qmk,
+                            hid: opt!($hid),
+                            evdev: opt!($evdev),
+                        };
+                        Some(&INFO)
+                    })*
+                }
+            }
+
+            fn from_name(name: &str) -> Option<Key> {
+                Some(match name {
+                    $($($name)|+ => Key::$key,)*
+                    _ => return None,
+                })
+            }
+        }
+    };
+}
 
 #[rustfmt::skip]
-#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
-pub enum Key {
-    Fn(u8),    F13,      F14,     F15,   F16,  F17,  F18, F19,    F20,   F21,  F22,       F23,         F24,                     VolumeUp, VolumeDown, VolumeMute,
-    Esc,       F1,       F2,      F3,    F4,   F5,   F6,  F7,     F8,    F9,   F10,       F11,         F12,                     PrintScreen,  ScrollLock, Pause,
-    Grave,     One,      Two,     Three, Four, Five, Six, Seven,  Eight, Nine, Zero,      Minus,       Equal,        Backspace, Insert, Home,       PageUp,   Numlock, KpSlash, KpAsterisk, KpMinus,
-    Tab,       Q,        W,       E,     R,    T,    Y,   U,      I,     O,    P,         LeftBracket, RightBracket, Backslash, Delete, End,        PageDown, Kp7,     Kp8,     Kp9,        KpPlus,
-    CapsLock,  A,        S,       D,     F,    G,    H,   J,      K,     L,    Semicolon, Apostrophe,                    Enter,                               Kp4,     Kp5,     Kp6,
-    LeftShift, Z,        X,       C,     V,    B,    N,   M,      Comma, Dot,  Slash,                               RightShift,         Up,                   Kp1,     Kp2,     Kp3,        KpEqual,
-    LeftCtrl,  LeftMeta, LeftAlt, Space,                                       RightAlt,  RightMeta,   Menu,         RightCtrl, Left,   Down,       Right,    Kp0,              KpDot,      KpEnter,
+keys! {
+    // Key             Names                                    Kanata      QMK                     HID           evdev
+
+    // Function keys
+    Esc                ["KeyEsc", "esc"]                        "esc"       "KC_ESCAPE"             (0x07, 0x29)  1;
+    F1                 ["KeyF1", "f1"]                          "f1"        "KC_F1"                 (0x07, 0x3A)  59;
+    F2                 ["KeyF2", "f2"]                          "f2"        "KC_F2"                 (0x07, 0x3B)  60;
+    F3                 ["KeyF3", "f3"]                          "f3"        "KC_F3"                 (0x07, 0x3C)  61;
+    F4                 ["KeyF4", "f4"]                          "f4"        "KC_F4"                 (0x07, 0x3D)  62;
+    F5                 ["KeyF5", "f5"]                          "f5"        "KC_F5"                 (0x07, 0x3E)  63;
+    F6                 ["KeyF6", "f6"]                          "f6"        "KC_F6"                 (0x07, 0x3F)  64;
+    F7                 ["KeyF7", "f7"]                          "f7"        "KC_F7"                 (0x07, 0x40)  65;
+    F8                 ["KeyF8", "f8"]                          "f8"        "KC_F8"                 (0x07, 0x41)  66;
+    F9                 ["KeyF9", "f9"]                          "f9"        "KC_F9"                 (0x07, 0x42)  67;
+    F10                ["KeyF10", "f10"]                        "f10"       "KC_F10"                (0x07, 0x43)  68;
+    F11                ["KeyF11", "f11"]                        "f11"       "KC_F11"                (0x07, 0x44)  87;
+    F12                ["KeyF12", "f12"]                        "f12"       "KC_F12"                (0x07, 0x45)  88;
+    F13                ["KeyF13", "f13"]                        "f13"       "KC_F13"                (0x07, 0x68)  183;
+    F14                ["KeyF14", "f14"]                        "f14"       "KC_F14"                (0x07, 0x69)  184;
+    F15                ["KeyF15", "f15"]                        "f15"       "KC_F15"                (0x07, 0x6A)  185;
+    F16                ["KeyF16", "f16"]                        "f16"       "KC_F16"                (0x07, 0x6B)  186;
+    F17                ["KeyF17", "f17"]                        "f17"       "KC_F17"                (0x07, 0x6C)  187;
+    F18                ["KeyF18", "f18"]                        "f18"       "KC_F18"                (0x07, 0x6D)  188;
+    F19                ["KeyF19", "f19"]                        "f19"       "KC_F19"                (0x07, 0x6E)  189;
+    F20                ["KeyF20", "f20"]                        "f20"       "KC_F20"                (0x07, 0x6F)  190;
+    F21                ["KeyF21", "f21"]                        "f21"       "KC_F21"                (0x07, 0x70)  191;
+    F22                ["KeyF22", "f22"]                        "f22"       "KC_F22"                (0x07, 0x71)  192;
+    F23                ["KeyF23", "f23"]                        "f23"       "KC_F23"                (0x07, 0x72)  193;
+    F24                ["KeyF24", "f24"]                        "f24"       "KC_F24"                (0x07, 0x73)  194;
+
+    // Main block
+    Grave              ["Backquote", "`", "grv"]                "grv"       "KC_GRAVE"              (0x07, 0x35)  41;
+    One                ["Digit1", "1"]                          "1"         "KC_1"                  (0x07, 0x1E)  2;
+    Two                ["Digit2", "2"]                          "2"         "KC_2"                  (0x07, 0x1F)  3;
+    Three              ["Digit3", "3"]                          "3"         "KC_3"                  (0x07, 0x20)  4;
+    Four               ["Digit4", "4"]                          "4"         "KC_4"                  (0x07, 0x21)  5;
+    Five               ["Digit5", "5"]                          "5"         "KC_5"                  (0x07, 0x22)  6;
+    Six                ["Digit6", "6"]                          "6"         "KC_6"                  (0x07, 0x23)  7;
+    Seven              ["Digit7", "7"]                          "7"         "KC_7"                  (0x07, 0x24)  8;
+    Eight              ["Digit8", "8"]                          "8"         "KC_8"                  (0x07, 0x25)  9;
+    Nine               ["Digit9", "9"]                          "9"         "KC_9"                  (0x07, 0x26)  10;
+    Zero               ["Digit0", "0"]                          "0"         "KC_0"                  (0x07, 0x27)  11;
+    Minus              ["Minus", "-"]                           "-"         "KC_MINUS"              (0x07, 0x2D)  12;
+    Equal              ["Equal", "="]                           "="         "KC_EQUAL"              (0x07, 0x2E)  13;
+    Backspace          ["Backspace", "bks", "bspc"]             "bks"       "KC_BACKSPACE"          (0x07, 0x2A)  14;
+    Tab                ["Tab", "tab"]                           "tab"       "KC_TAB"                (0x07, 0x2B)  15;
+    Q                  ["KeyQ", "q"]                            "q"         "KC_Q"                  (0x07, 0x14)  16;
+    W                  ["KeyW", "w"]                            "w"         "KC_W"                  (0x07, 0x1A)  17;
+    E                  ["KeyE", "e"]                            "e"         "KC_E"                  (0x07, 0x08)  18;
+    R                  ["KeyR", "r"]                            "r"         "KC_R"                  (0x07, 0x15)  19;
+    T                  ["KeyT", "t"]                            "t"         "KC_T"                  (0x07, 0x17)  20;
+    Y                  ["KeyY", "y"]                            "y"         "KC_Y"                  (0x07, 0x1C)  21;
+    U                  ["KeyU", "u"]                            "u"         "KC_U"                  (0x07, 0x18)  22;
+    I                  ["KeyI", "i"]                            "i"         "KC_I"                  (0x07, 0x0C)  23;
+    O                  ["KeyO", "o"]                            "o"         "KC_O"                  (0x07, 0x12)  24;
+    P                  ["KeyP", "p"]                            "p"         "KC_P"                  (0x07, 0x13)  25;
+    LeftBracket        ["BracketLeft", "["]                     "["         "KC_LEFT_BRACKET"       (0x07, 0x2F)  26;
+    RightBracket       ["BracketRight", "]"]                    "]"         "KC_RIGHT_BRACKET"      (0x07, 0x30)  27;
+    Backslash          ["Backslash", "\\"]                      "\\"        "KC_BACKSLASH"          (0x07, 0x31)  43;
+    CapsLock           ["CapsLock", "caps"]                     "caps"      "KC_CAPS_LOCK"          (0x07, 0x39)  58;
+    A                  ["KeyA", "a"]                            "a"         "KC_A"                  (0x07, 0x04)  30;
+    S                  ["KeyS", "s"]                            "s"         "KC_S"                  (0x07, 0x16)  31;
+    D                  ["KeyD", "d"]                            "d"         "KC_D"                  (0x07, 0x07)  32;
+    F                  ["KeyF", "f"]                            "f"         "KC_F"                  (0x07, 0x09)  33;
+    G                  ["KeyG", "g"]                            "g"         "KC_G"                  (0x07, 0x0A)  34;
+    H                  ["KeyH", "h"]                            "h"         "KC_H"                  (0x07, 0x0B)  35;
+    J                  ["KeyJ", "j"]                            "j"         "KC_J"                  (0x07, 0x0D)  36;
+    K                  ["KeyK", "k"]                            "k"         "KC_K"                  (0x07, 0x0E)  37;
+    L                  ["KeyL", "l"]                            "l"         "KC_L"                  (0x07, 0x0F)  38;
+    Semicolon          ["Semicolon", ";"]                       ";"         "KC_SEMICOLON"          (0x07, 0x33)  39;
+    Apostrophe         ["Quote", "'"]                           "'"         "KC_QUOTE"              (0x07, 0x34)  40;
+    Enter              ["Enter", "ent", "enter"]                "enter"     "KC_ENTER"              (0x07, 0x28)  28;
+    Z                  ["KeyZ", "z"]                            "z"         "KC_Z"                  (0x07, 0x1D)  44;
+    X                  ["KeyX", "x"]                            "x"         "KC_X"                  (0x07, 0x1B)  45;
+    C                  ["KeyC", "c"]                            "c"         "KC_C"                  (0x07, 0x06)  46;
+    V                  ["KeyV", "v"]                            "v"         "KC_V"                  (0x07, 0x19)  47;
+    B                  ["KeyB", "b"]                            "b"         "KC_B"                  (0x07, 0x05)  48;
+    N                  ["KeyN", "n"]                            "n"         "KC_N"                  (0x07, 0x11)  49;
+    M                  ["KeyM", "m"]                            "m"         "KC_M"                  (0x07, 0x10)  50;
+    Comma              ["Comma", ","]                           ","         "KC_COMMA"              (0x07, 0x36)  51;
+    Dot                ["Period", "."]                          "."         "KC_DOT"                (0x07, 0x37)  52;
+    Slash              ["Slash", "/"]                           "/"         "KC_SLASH"              (0x07, 0x38)  53;
+    Space              ["Space", "spc"]                         "spc"       "KC_SPACE"              (0x07, 0x2C)  57;
+    Menu               ["Menu", "menu"]                         "menu"      "KC_APPLICATION"        (0x07, 0x65)  127;
+
+    // Modifiers
+    LeftShift          ["LeftShift", "sft", "lsft", "LS", "S"]  "lsft"      "KC_LEFT_SHIFT"         (0x07, 0xE1)  42;
+    RightShift         ["RightShift", "rsft", "RS"]             "rsft"      "KC_RIGHT_SHIFT"        (0x07, 0xE5)  54;
+    LeftCtrl           ["LeftCtrl", "lctl", "ctl", "LC", "C"]   "lctl"      "KC_LEFT_CTRL"          (0x07, 0xE0)  29;
+    RightCtrl          ["RightCtrl", "rctl", "RC"]              "rctl"      "KC_RIGHT_CTRL"         (0x07, 0xE4)  97;
+    LeftMeta           ["LeftMeta", "lmeta", "meta", "LM", "M"] "lmeta"     "KC_LEFT_GUI"           (0x07, 0xE3)  125;
+    RightMeta          ["RightMeta", "rmeta", "RM"]             "rmeta"     "KC_RIGHT_GUI"          (0x07, 0xE7)  126;
+    LeftAlt            ["LeftAlt", "lalt", "alt", "LA", "A"]    "lalt"      "KC_LEFT_ALT"           (0x07, 0xE2)  56;
+    RightAlt           ["RightAlt", "ralt", "RA"]               "ralt"      "KC_RIGHT_ALT"          (0x07, 0xE6)  100;
+
+    // Navigation
+    PrintScreen        ["PrintScreen", "prnt"]                  "prnt"      "KC_PRINT_SCREEN"       (0x07, 0x46)  99;
+    ScrollLock         ["ScrollLock", "sclk"]                   "sclk"      "KC_SCROLL_LOCK"        (0x07, 0x47)  70;
+    Pause              ["Pause", "pause"]                       "pause"     "KC_PAUSE"              (0x07, 0x48)  119;
+    Insert             ["Insert", "ins"]                        "ins"       "KC_INSERT"             (0x07, 0x49)  110;
+    Home               ["Home", "home"]                         "home"      "KC_HOME"               (0x07, 0x4A)  102;
+    PageUp             ["PageUp", "pgup"]                       "pgup"      "KC_PAGE_UP"            (0x07, 0x4B)  104;
+    Delete             ["Delete", "del"]                        "del"       "KC_DELETE"             (0x07, 0x4C)  111;
+    End                ["End", "end"]                           "end"       "KC_END"                (0x07, 0x4D)  107;
+    PageDown           ["PageDown", "pgdn"]                     "pgdn"      "KC_PAGE_DOWN"          (0x07, 0x4E)  109;
+    Up                 ["ArrowUp", "up"]                        "up"        "KC_UP"                 (0x07, 0x52)  103;
+    Left               ["ArrowLeft", "lt"]                      "left"      "KC_LEFT"               (0x07, 0x50)  105;
+    Down               ["ArrowDown", "dn"]                      "down"      "KC_DOWN"               (0x07, 0x51)  108;
+    Right              ["ArrowRight", "rt"]                     "right"     "KC_RIGHT"              (0x07, 0x4F)  106;
+
+    // Keypad
+    Numlock            ["Numlock", "nlck"]                      "nlck"      "KC_NUM_LOCK"           (0x07, 0x53)  69;
+    KpSlash            ["NumpadSlash", "kp/"]                   "kp/"       "KC_KP_SLASH"           (0x07, 0x54)  98;
+    KpAsterisk         ["NumpadAsterisk", "kp*"]                "kp*"       "KC_KP_ASTERISK"        (0x07, 0x55)  55;
+    KpMinus            ["NumpadMinus", "kp-"]                   "kp-"       "KC_KP_MINUS"           (0x07, 0x56)  74;
+    KpPlus             ["NumpadPlus", "kp+"]                    "kp+"       "KC_KP_PLUS"            (0x07, 0x57)  78;
+    KpEnter            ["NumpadEnter", "kprt"]                  "kprt"      "KC_KP_ENTER"           (0x07, 0x58)  96;
+    Kp1                ["Numpad1", "kp1"]                       "kp1"       "KC_KP_1"               (0x07, 0x59)  79;
+    Kp2                ["Numpad2", "kp2"]                       "kp2"       "KC_KP_2"               (0x07, 0x5A)  80;
+    Kp3                ["Numpad3", "kp3"]                       "kp3"       "KC_KP_3"               (0x07, 0x5B)  81;
+    Kp4                ["Numpad4", "kp4"]                       "kp4"       "KC_KP_4"               (0x07, 0x5C)  75;
+    Kp5                ["Numpad5", "kp5"]                       "kp5"       "KC_KP_5"               (0x07, 0x5D)  76;
+    Kp6                ["Numpad6", "kp6"]                       "kp6"       "KC_KP_6"               (0x07, 0x5E)  77;
+    Kp7                ["Numpad7", "kp7"]                       "kp7"       "KC_KP_7"               (0x07, 0x5F)  71;
+    Kp8                ["Numpad8", "kp8"]                       "kp8"       "KC_KP_8"               (0x07, 0x60)  72;
+    Kp9                ["Numpad9", "kp9"]                       "kp9"       "KC_KP_9"               (0x07, 0x61)  73;
+    Kp0                ["Numpad0", "kp0"]                       "kp0"       "KC_KP_0"               (0x07, 0x62)  82;
+    KpDot              ["NumpadDecimal", "kp."]                 "kp."       "KC_KP_DOT"             (0x07, 0x63)  83;
+    KpEqual            ["NumpadEqual", "kp="]                   "kp="       "KC_KP_EQUAL"           (0x07, 0x67)  117;
 
     // Media
-    MediaPlayPause,
+    VolumeUp           ["VolumeUp", "volu", "vol+"]             "volu"      "KC_AUDIO_VOL_UP"       (0x0C, 0xE9)  115;
+    VolumeDown         ["VolumeDown", "vold", "vol-"]           "vold"      "KC_AUDIO_VOL_DOWN"     (0x0C, 0xEA)  114;
+    VolumeMute         ["VolumeMute", "mute"]                   "mute"      "KC_AUDIO_MUTE"         (0x0C, 0xE2)  113;
+    MediaPlayPause     ["MediaPlayPause", "pp"]                 "pp"        "KC_MEDIA_PLAY_PAUSE"   (0x0C, 0xCD)  164;
+    MediaNextTrack     ["MediaTrackNext", "next"]               "next"      "KC_MEDIA_NEXT_TRACK"   (0x0C, 0xB5)  163;
+    MediaPrevTrack     ["MediaTrackPrevious", "prev"]           "prev"      "KC_MEDIA_PREV_TRACK"   (0x0C, 0xB6)  165;
+    MediaStop          ["MediaStop", "stop"]                    "stop"      "KC_MEDIA_STOP"         (0x0C, 0xB7)  166;
+    MediaFastForward   ["MediaFastForward", "ffwd"]             "ffwd"      "KC_MEDIA_FAST_FORWARD" (0x0C, 0xB3)  208;
+    MediaRewind        ["MediaRewind", "rewind"]                "rewind"    "KC_MEDIA_REWIND"       (0x0C, 0xB4)  168;
+    MediaEject         ["Eject", "eject"]                       "eject"     "KC_MEDIA_EJECT"        (0x0C, 0xB8)  161;
+    MediaSelect        ["MediaSelect"]                          _           "KC_MEDIA_SELECT"       (0x0C, 0x183) 171;
+    BrightnessUp       ["BrightnessUp", "brup"]                 "brup"      "KC_BRIGHTNESS_UP"      (0x0C, 0x6F)  225;
+    BrightnessDown     ["BrightnessDown", "brdn"]               "brdn"      "KC_BRIGHTNESS_DOWN"    (0x0C, 0x70)  224;
 
+    // Applications and browser
+    Calculator         ["LaunchApp2", "calc"]                   "calc"      "KC_CALCULATOR"         (0x0C, 0x192) 140;
+    Mail               ["LaunchMail", "mail"]                   "mail"      "KC_MAIL"               (0x0C, 0x18A) 155;
+    MyComputer         ["LaunchApp1", "mycomputer"]             _           "KC_MY_COMPUTER"        (0x0C, 0x194) 144;
+    BrowserSearch      ["BrowserSearch"]                        "search"    "KC_WWW_SEARCH"         (0x0C, 0x221) 217;
+    BrowserHome        ["BrowserHome"]                          _           "KC_WWW_HOME"           (0x0C, 0x223) 172;
+    BrowserBack        ["BrowserBack"]                          "bck"       "KC_WWW_BACK"           (0x0C, 0x224) 158;
+    BrowserForward     ["BrowserForward"]                       "fwd"       "KC_WWW_FORWARD"        (0x0C, 0x225) 159;
+    BrowserStop        ["BrowserStop"]                          _           "KC_WWW_STOP"           (0x0C, 0x226) 128;
+    BrowserRefresh     ["BrowserRefresh"]                       "refresh"   "KC_WWW_REFRESH"        (0x0C, 0x227) 173;
+    BrowserFavorites   ["BrowserFavorites"]                     _           "KC_WWW_FAVORITES"      (0x0C, 0x22A) 156;
 
+    // System
+    SystemPower        ["Power", "power"]                       "power"     "KC_SYSTEM_POWER"       (0x01, 0x81)  116;
+    SystemSleep        ["Sleep", "sleep"]                       "sleep"     "KC_SYSTEM_SLEEP"       (0x01, 0x82)  142;
+    SystemWake         ["WakeUp", "wake"]                       "wakeup"    "KC_SYSTEM_WAKE"        (0x01, 0x83)  143;
+
+    // ISO and JIS. Linux reports the non-US # key as backslash, so it has no
+    // evdev code of its own
+    NonUsBackslash     ["IntlBackslash", "nubs"]                "102d"      "KC_NONUS_BACKSLASH"    (0x07, 0x64)  86;
+    NonUsHash          ["IntlHash", "nuhs"]                     _           "KC_NONUS_HASH"         (0x07, 0x32)  _;
+    IntlRo             ["IntlRo", "ro"]                         "ro"        "KC_INTERNATIONAL_1"    (0x07, 0x87)  89;
+    KanaMode           ["KanaMode", "kana"]                     "kana"      "KC_INTERNATIONAL_2"    (0x07, 0x88)  93;
+    IntlYen            ["IntlYen", "yen"]                       "yen"       "KC_INTERNATIONAL_3"    (0x07, 0x89)  124;
+    Henkan             ["Convert", "henk"]                      "henk"      "KC_INTERNATIONAL_4"    (0x07, 0x8A)  92;
+    Muhenkan           ["NonConvert", "mhnk"]                   "mhnk"      "KC_INTERNATIONAL_5"    (0x07, 0x8B)  94;
+
+    // Language keys, e.g. Hangul/Hanja or Kana/Eisu. Linux maps no event code
+    // to the HID usages of Lang6-9
+    Lang1              ["Lang1", "lang1"]                       "lang1"     "KC_LANGUAGE_1"         (0x07, 0x90)  122;
+    Lang2              ["Lang2", "lang2"]                       "lang2"     "KC_LANGUAGE_2"         (0x07, 0x91)  123;
+    Lang3              ["Lang3", "lang3"]                       "lang3"     "KC_LANGUAGE_3"         (0x07, 0x92)  90;
+    Lang4              ["Lang4", "lang4"]                       "lang4"     "KC_LANGUAGE_4"         (0x07, 0x93)  91;
+    Lang5              ["Lang5", "lang5"]                       "lang5"     "KC_LANGUAGE_5"         (0x07, 0x94)  85;
+    Lang6              ["Lang6", "lang6"]                       "lang6"     "KC_LANGUAGE_6"         (0x07, 0x95)  _;
+    Lang7              ["Lang7", "lang7"]                       "lang7"     "KC_LANGUAGE_7"         (0x07, 0x96)  _;
+    Lang8              ["Lang8", "lang8"]                       "lang8"     "KC_LANGUAGE_8"         (0x07, 0x97)  _;
+    Lang9              ["Lang9", "lang9"]                       "lang9"     "KC_LANGUAGE_9"         (0x07, 0x98)  _;
+
+    // Mouse. HID and evdev move the cursor and the wheel with relative axes,
+    // not usages or key codes. QMK acceleration keys only exist in the
+    // firmware
+    MouseCursorUp      ["MouseCursorUp", "mcup"]                _           "KC_MS_UP"              _             _;
+    MouseCursorDown    ["MouseCursorDown", "mcdn"]              _           "KC_MS_DOWN"            _             _;
+    MouseCursorLeft    ["MouseCursorLeft", "mclt"]              _           "KC_MS_LEFT"            _             _;
+    MouseCursorRight   ["MouseCursorRight", "mcrt"]             _           "KC_MS_RIGHT"           _             _;
+    MouseWheelUp       ["MouseWheelUp", "mwup"]                 "mwu"       "KC_WH_U"               _             _;
+    MouseWheelDown     ["MouseWheelDown", "mwdn"]               "mwd"       "KC_WH_D"               _             _;
+    MouseWheelLeft     ["MouseWheelLeft", "mwlt"]               "mwl"       "KC_WH_L"               _             _;
+    MouseWheelRight    ["MouseWheelRight", "mwrt"]              "mwr"       "KC_WH_R"               _             _;
+    MouseButton1       ["MouseButton1", "m1"]                   "mlft"      "KC_MS_BTN1"            (0x09, 0x01)  272;
+    MouseButton2       ["MouseButton2", "m2"]                   "mrgt"      "KC_MS_BTN2"            (0x09, 0x02)  273;
+    MouseButton3       ["MouseButton3", "m3"]                   "mmid"      "KC_MS_BTN3"            (0x09, 0x03)  274;
+    MouseButton4       ["MouseButton4", "m4"]                   "mbck"      "KC_MS_BTN4"            (0x09, 0x04)  275;
+    MouseButton5       ["MouseButton5", "m5"]                   "mfwd"      "KC_MS_BTN5"            (0x09, 0x05)  276;
+    MouseAcceleration0 ["MouseAcceleration0"]                   _           "KC_MS_ACCEL0"          _             _;
+    MouseAcceleration1 ["MouseAcceleration1"]                   _           "KC_MS_ACCEL1"          _             _;
+    MouseAcceleration2 ["MouseAcceleration2"]                   _           "KC_MS_ACCEL2"          _             _;
 
-    // Mouse
-    MouseCursorUp, MouseCursorDown, MouseCursorLeft, MouseCursorRight,
-    MouseWheelUp, MouseWheelDown, MouseWheelLeft, MouseWheelRight,
-    MouseButton1, MouseButton2, MouseButton3, MouseButton4, MouseButton5,
-    MouseAcceleration0, MouseAcceleration1, MouseAcceleration2,
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
@@ -40,6 +294,46 @@ impl TryInto<usize> for &KeyIndex {
 }
 
 impl Key {
+    /// Kanata key name, `None` for `Fn`, the keys kanata only has as actions
+    /// and the keys kanata doesn't know
+    pub fn kanata(&self) -> Option<&'static str> {
+        self.info().and_then(|info| info.kanata)
+    }
+    /// QMK keycode name, `KC_NO` for `Fn`
+    pub fn qmk(&self) -> &'static str {
+        self.info().map_or("KC_NO", |info| info.qmk)
+    }
+
+    /// HID usage page and id. Media keys use the consumer page, power keys
+    /// the generic desktop page and mouse buttons the button page
+    pub fn hid_usage(&self) -> Option<(u16, u16)> {
+        self.info().and_then(|info| info.hid)
+    }
+    pub fn from_hid_usage(page: u16, id: u16) -> Option<Key> {
+        // Volume usages of the keyboard page, QMK sends the consumer ones
+        let usage = match (page, id) {
+            (0x07, 0x7F) => (0x0C, 0xE2),
+            (0x07, 0x80) => (0x0C, 0xE9),
+            (0x07, 0x81) => (0x0C, 0xEA),
+            usage => usage,
+        };
+        Self::ALL
+            .iter()
+            .find(|key| key.hid_usage() == Some(usage))
+            .copied()
+    }
+
+    /// Linux input event code, `KEY_*` or `BTN_*`
+    pub fn evdev_code(&self) -> Option<u16> {
+        self.info().and_then(|info| info.evdev)
+    }
+    pub fn from_evdev_code(code: u16) -> Option<Key> {
+        Self::ALL
+            .iter()
+            .find(|key| key.evdev_code() == Some(code))
+            .copied()
+    }
+
     pub fn from_digit(c: char) -> Self {
         match c {
             '0' => Key::Kp0,
@@ -73,166 +367,102 @@ impl Key {
 impl FromStr for Key {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Some(d) = s.strip_prefix("fn") {
+        if let Some(d) = s.strip_prefix("fn").or(s.strip_prefix("KeyFn")) {
             let num: u8 = d.parse().map_err(|_| ())?;
             return Ok(Self::Fn(num));
         }
-        if let Some(d) = s.strip_prefix("KeyFn") {
-            let num: u8 = d.parse().map_err(|_| ())?;
-            return Ok(Self::Fn(num));
+        Self::from_name(s).ok_or(())
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Fn(x) => write!(f, "fn{}", x),
+            key => f.write_str(key.info().map_or("", |info| info.names[0])),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Keys kanata has only as actions, lowered by the kanata backend
+    const KANATA_ACTIONS: &[Key] = &[
+        Key::MouseCursorUp,
+        Key::MouseCursorDown,
+        Key::MouseCursorLeft,
+        Key::MouseCursorRight,
+        Key::MouseAcceleration0,
+        Key::MouseAcceleration1,
+        Key::MouseAcceleration2,
+    ];
+
+    /// Keys kanata has no name of their own for
+    const KANATA_UNKNOWN: &[Key] = &[
+        Key::NonUsHash,
+        Key::MediaSelect,
+        Key::MyComputer,
+        Key::BrowserHome,
+        Key::BrowserStop,
+        Key::BrowserFavorites,
+    ];
+
+    #[test]
+    fn every_key_has_every_target() {
+        let mut names = HashSet::new();
+        let mut qmk = HashSet::new();
+        for key in Key::ALL {
+            let info = key.info().unwrap();
+            for name in info.names {
+                assert!(names.insert(*name), "{:?} has a duplicate name {}", key, name);
+                assert_eq!(name.parse(), Ok(*key));
+            }
+            assert_eq!(key.to_string().parse(), Ok(*key));
+            assert!(qmk.insert(info.qmk), "{:?} has a duplicate QMK name", key);
+            assert!(info.qmk.starts_with("KC_"), "{:?}", key);
+            assert_eq!(
+                info.kanata.is_none(),
+                KANATA_ACTIONS.contains(key) || KANATA_UNKNOWN.contains(key),
+                "{:?}",
+                key
+            );
+        }
+        assert_eq!(names.len(), Key::NAMES.len());
+        assert_eq!("fn2".parse::<Key>().map(|k| k.to_string()), Ok("fn2".into()));
+    }
+
+    #[test]
+    fn hid_and_evdev_round_trip() {
+        for key in Key::ALL {
+            if let Some((page, id)) = key.hid_usage() {
+                assert_eq!(Key::from_hid_usage(page, id), Some(*key));
+            }
+            if let Some(code) = key.evdev_code() {
+                assert_eq!(Key::from_evdev_code(code), Some(*key));
+            }
         }
-        Ok(match s {
-            "KeyEsc" | "esc" => Self::Esc,
-            "KeyF1" | "f1" => Self::F1,
-            "KeyF2" | "f2" => Self::F2,
-            "KeyF3" | "f3" => Self::F3,
-            "KeyF4" | "f4" => Self::F4,
-            "KeyF5" | "f5" => Self::F5,
-            "KeyF6" | "f6" => Self::F6,
-            "KeyF7" | "f7" => Self::F7,
-            "KeyF8" | "f8" => Self::F8,
-            "KeyF9" | "f9" => Self::F9,
-            "KeyF10" | "f10" => Self::F10,
-            "KeyF11" | "f11" => Self::F11,
-            "KeyF12" | "f12" => Self::F12,
-            "KeyF13" | "f13" => Self::F13,
-            "KeyF14" | "f14" => Self::F14,
-            "KeyF15" | "f15" => Self::F15,
-            "KeyF16" | "f16" => Self::F16,
-            "KeyF17" | "f17" => Self::F17,
-            "KeyF18" | "f18" => Self::F18,
-            "KeyF19" | "f19" => Self::F19,
-            "KeyF20" | "f20" => Self::F20,
-            "KeyF21" | "f21" => Self::F21,
-            "KeyF22" | "f22" => Self::F22,
-            "KeyF23" | "f23" => Self::F23,
-            "KeyF24" | "f24" => Self::F24,
-            "PrintScreen" => Self::PrintScreen,
-            "ScrollLock" => Self::ScrollLock,
-            "Pause" => Self::Pause,
-
-            "Backquote" | "`" | "grv" => Self::Grave,
-            "Digit1" | "1" => Self::One,
-            "Digit2" | "2" => Self::Two,
-            "Digit3" | "3" => Self::Three,
-            "Digit4" | "4" => Self::Four,
-            "Digit5" | "5" => Self::Five,
-            "Digit6" | "6" => Self::Six,
-            "Digit7" | "7" => Self::Seven,
-            "Digit8" | "8" => Self::Eight,
-            "Digit9" | "9" => Self::Nine,
-            "Digit0" | "0" => Self::Zero,
-            "Minus" | "-" => Self::Minus,
-            "Equal" | "=" => Self::Equal,
-            "Backspace" | "bks" | "bspc" => Self::Backspace,
-            "Insert" | "ins" => Self::Insert,
-            "Home" | "home" => Self::Home,
-            "PageUp" | "pgup" => Self::PageUp,
-            "Numlock" => Self::Numlock,
-
-            "Tab" | "tab" => Self::Tab,
-            "KeyQ" | "q" => Self::Q,
-            "KeyW" | "w" => Self::W,
-            "KeyE" | "e" => Self::E,
-            "KeyR" | "r" => Self::R,
-            "KeyT" | "t" => Self::T,
-            "KeyY" | "y" => Self::Y,
-            "KeyU" | "u" => Self::U,
-            "KeyI" | "i" => Self::I,
-            "KeyO" | "o" => Self::O,
-            "KeyP" | "p" => Self::P,
-            "BracketLeft" | "[" => Self::LeftBracket,
-            "BracketRight" | "]" => Self::RightBracket,
-            "Backslash" | "\\" => Self::Backslash,
-            "Delete" | "del" => Self::Delete,
-            "End" | "end" => Self::End,
-            "PageDown" | "pgdn" => Self::PageDown,
-
-            "CapsLock" | "caps" => Self::CapsLock,
-            "KeyA" | "a" => Self::A,
-            "KeyS" | "s" => Self::S,
-            "KeyD" | "d" => Self::D,
-            "KeyF" | "f" => Self::F,
-            "KeyG" | "g" => Self::G,
-            "KeyH" | "h" => Self::H,
-            "KeyJ" | "j" => Self::J,
-            "KeyK" | "k" => Self::K,
-            "KeyL" | "l" => Self::L,
-            "Semicolon" | ";" => Self::Semicolon,
-            "Quote" | "'" => Self::Apostrophe,
-            "Enter" | "ent" | "enter" => Self::Enter,
-
-            "KeyZ" | "z" => Self::Z,
-            "KeyX" | "x" => Self::X,
-            "KeyC" | "c" => Self::C,
-            "KeyV" | "v" => Self::V,
-            "KeyB" | "b" => Self::B,
-            "KeyN" | "n" => Self::N,
-            "KeyM" | "m" => Self::M,
-            "Comma" | "," => Self::Comma,
-            "Period" | "." => Self::Dot,
-            "Slash" | "/" => Self::Slash,
-
-            "Numpad0" | "kp0" => Self::Kp0,
-            "Numpad1" | "kp1" => Self::Kp1,
-            "Numpad2" | "kp2" => Self::Kp2,
-            "Numpad3" | "kp3" => Self::Kp3,
-            "Numpad4" | "kp4" => Self::Kp4,
-            "Numpad5" | "kp5" => Self::Kp5,
-            "Numpad6" | "kp6" => Self::Kp6,
-            "Numpad7" | "kp7" => Self::Kp7,
-            "Numpad8" | "kp8" => Self::Kp8,
-            "Numpad9" | "kp9" => Self::Kp9,
-            "NumpadPlus" | "kp+" => Self::KpPlus,
-            "NumpadEnter" | "kprt" => Self::KpEnter,
-            "NumpadDecimal" | "kp." => Self::KpDot,
-            "NumpadSlash" | "kp/" => Self::KpSlash,
-            "NumpadAsterisk" | "kp*" => Self::KpAsterisk,
-            "NumpadMinus" | "kp-" => Self::KpMinus,
-
-            "LeftShift" | "sft" | "lsft" | "LS" | "S" => Self::LeftShift,
-            "RightShift" | "rsft" | "RS" => Self::RightShift,
-
-            "LeftCtrl" | "lctl" | "ctl" | "LC" | "C" => Self::LeftCtrl,
-            "RightCtrl" | "rctl" | "RC" => Self::RightCtrl,
-
-            "LeftMeta" | "lmeta" | "meta" | "LM" | "M" => Self::LeftMeta,
-            "RightMeta" | "rmeta" | "RM" => Self::RightMeta,
-
-            "LeftAlt" | "lalt" | "alt" | "LA" | "A" => Self::LeftAlt,
-            "RightAlt" | "ralt" | "RA" => Self::RightAlt,
-
-            "Space" | "spc" => Self::Space,
-            "Menu" | "menu" => Self::Menu,
-
-            "ArrowLeft" | "lt" => Self::Left,
-            "ArrowDown" | "dn" => Self::Down,
-            "ArrowUp" | "up" => Self::Up,
-            "ArrowRight" | "rt" => Self::Right,
-
-            "VolumeUp" | "volu" | "vol+" => Self::VolumeUp,
-            "VolumeDown" | "vold" | "vol-" => Self::VolumeDown,
-            "VolumeMute" | "mute" => Self::VolumeMute,
-            "MediaPlayPause" => Self::MediaPlayPause,
-
-            "MouseCursorUp" | "mcup" => Self::MouseCursorUp,
-            "MouseCursorDown" | "mcdn" => Self::MouseCursorDown,
-            "MouseCursorLeft" | "mclt" => Self::MouseCursorLeft,
-            "MouseCursorRight" | "mcrt" => Self::MouseCursorRight,
-            "MouseWheelUp" | "mwup" => Self::MouseWheelUp,
-            "MouseWheelDown" | "mwdn" => Self::MouseWheelDown,
-            "MouseWheelLeft" | "mwlt" => Self::MouseWheelLeft,
-            "MouseWheelRight" | "mwrt" => Self::MouseWheelRight,
-            "MouseButton1" | "m1" => Self::MouseButton1,
-            "MouseButton2" | "m2" => Self::MouseButton2,
-            "MouseButton3" | "m3" => Self::MouseButton3,
-            "MouseButton4" | "m4" => Self::MouseButton4,
-            "MouseButton5" | "m5" => Self::MouseButton5,
-            "MouseAcceleration0" => Self::MouseAcceleration0,
-            "MouseAcceleration1" => Self::MouseAcceleration1,
-            "MouseAcceleration2" => Self::MouseAcceleration2,
-
-            _ => return Err(()),
-        })
+        let no_hid: Vec<_> = Key::ALL.iter().filter(|k| k.hid_usage().is_none()).collect();
+        assert!(no_hid.iter().all(|k| k.qmk().starts_with("KC_MS_") || k.qmk().starts_with("KC_WH_")));
+        let no_evdev: Vec<_> = Key::ALL
+            .iter()
+            .filter(|k| k.evdev_code().is_none() && k.hid_usage().is_some())
+            .collect();
+        assert_eq!(
+            no_evdev,
+            [
+                &Key::NonUsHash,
+                &Key::Lang6,
+                &Key::Lang7,
+                &Key::Lang8,
+                &Key::Lang9
+            ]
+        );
+        assert_eq!(Key::from_hid_usage(0x07, 0x04), Some(Key::A));
+        assert_eq!(Key::from_hid_usage(0x07, 0x80), Some(Key::VolumeUp));
+        assert_eq!(Key::from_evdev_code(30), Some(Key::A));
+        assert_eq!(Key::Fn(1).hid_usage(), None);
     }
 }
@@ -0,0 +1,155 @@
+use crate::keys::Key;
+use std::{
+    fmt,
+    ops::{BitAnd, BitOr, BitXor, Sub},
+    str::FromStr,
+};
+
+/// Set of modifiers, left and right ones are distinct. The bits are the HID
+/// modifier byte, also used by QMK as `MOD_BIT_*`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Mods(u8);
+
+/// Modifier keys in the bit order
+const KEYS: [Key; 8] = [
+    Key::LeftCtrl,
+    Key::LeftShift,
+    Key::LeftAlt,
+    Key::LeftMeta,
+    Key::RightCtrl,
+    Key::RightShift,
+    Key::RightAlt,
+    Key::RightMeta,
+];
+
+/// Kanata prefixes in the bit order
+const KANATA: [&str; 8] = ["C", "S", "A", "M", "RC", "RS", "RA", "RM"];
+
+impl Mods {
+    pub const NONE: Mods = Mods(0);
+    pub const LCTRL: Mods = Mods(0x01);
+    pub const LSHIFT: Mods = Mods(0x02);
+    pub const LALT: Mods = Mods(0x04);
+    pub const LMETA: Mods = Mods(0x08);
+    pub const RCTRL: Mods = Mods(0x10);
+    pub const RSHIFT: Mods = Mods(0x20);
+    pub const RALT: Mods = Mods(0x40);
+    pub const RMETA: Mods = Mods(0x80);
+
+    /// The modifier of `key`, `None` if it is not a modifier
+    pub fn from_key(key: &Key) -> Option<Mods> {
+        KEYS.iter().position(|k| k == key).map(|i| Mods(1 << i))
+    }
+
+    /// Modifiers of `keys`, the first key that is not a modifier is the error
+    pub fn from_keys<'a>(keys: impl IntoIterator<Item = &'a Key>) -> Result<Mods, &'a Key> {
+        keys.into_iter().try_fold(Mods::NONE, |acc, key| {
+            Mods::from_key(key).map(|m| acc | m).ok_or(key)
+        })
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+    pub fn contains(&self, other: Mods) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Modifier keys of the set, left ones first
+    pub fn keys(&self) -> impl Iterator<Item = Key> + '_ {
+        KEYS.iter()
+            .enumerate()
+            .filter(|(i, _)| self.0 & (1 << i) != 0)
+            .map(|(_, k)| *k)
+    }
+
+    /// Kanata prefix without the trailing dash, e.g. `C-S` of `C-S-a`
+    pub fn kanata(&self) -> String {
+        KANATA
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| self.0 & (1 << i) != 0)
+            .map(|(_, p)| *p)
+            .collect::<Vec<_>>()
+            .join("-")
+    }
+}
+
+impl BitOr for Mods {
+    type Output = Mods;
+    fn bitor(self, rhs: Mods) -> Mods {
+        Mods(self.0 | rhs.0)
+    }
+}
+impl BitAnd for Mods {
+    type Output = Mods;
+    fn bitand(self, rhs: Mods) -> Mods {
+        Mods(self.0 & rhs.0)
+    }
+}
+impl BitXor for Mods {
+    type Output = Mods;
+    fn bitxor(self, rhs: Mods) -> Mods {
+        Mods(self.0 ^ rhs.0)
+    }
+}
+impl Sub for Mods {
+    type Output = Mods;
+    fn sub(self, rhs: Mods) -> Mods {
+        Mods(self.0 & !rhs.0)
+    }
+}
+
+/// Parses `C-S-A-M` prefixes, any modifier name works: `lctl-RS`
+impl FromStr for Mods {
+    type Err = ();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split('-').try_fold(Mods::NONE, |acc, name| {
+            let key = name.parse::<Key>()?;
+            Mods::from_key(&key).map(|m| acc | m).ok_or(())
+        })
+    }
+}
+
+impl fmt::Display for Mods {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.kanata())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format() {
+        let mods: Mods = "C-S-RA".parse().unwrap();
+        assert_eq!(mods, Mods::LCTRL | Mods::LSHIFT | Mods::RALT);
+        assert_eq!(mods.kanata(), "C-S-RA");
+        assert_eq!(mods.bits(), 0x43);
+        assert_eq!(
+            mods.keys().collect::<Vec<_>>(),
+            [Key::LeftCtrl, Key::LeftShift, Key::RightAlt]
+        );
+        assert_eq!("C-a".parse::<Mods>(), Err(()));
+        assert_eq!(Mods::from_keys(&[Key::LeftMeta, Key::A]), Err(&Key::A));
+    }
+
+    #[test]
+    fn set_operations() {
+        let a = Mods::LCTRL | Mods::LSHIFT;
+        let b = Mods::LSHIFT | Mods::RMETA;
+        assert_eq!(a & b, Mods::LSHIFT);
+        assert_eq!(a - b, Mods::LCTRL);
+        assert_eq!(a ^ b, Mods::LCTRL | Mods::RMETA);
+        assert!(a.contains(Mods::LCTRL) && !a.contains(b));
+        assert_eq!((a | b).len(), 3);
+        assert!(Mods::NONE.is_empty());
+    }
+}
@@ -2,8 +2,8 @@ mod matrix;
 mod vial;
 
 use keys::keys::{Key, KeyIndex};
-pub use matrix::{Item as MatrixItem, Matrix, parse as parse_matix};
-use s_expression::Expr;
+pub use matrix::{Hand, Item as MatrixItem, Matrix, parse as parse_matix};
+use s_expression::{Code, Error, Expr, Span};
 use std::collections::HashMap;
 pub use vial::{Item as VialItem, Vial, parse as parse_vial};
 
@@ -15,19 +15,28 @@ pub struct Keyboard {
     pub meta: String,
 }
 
-pub fn parse_keymap(lst: &Vec<Expr>) -> Result<HashMap<Key, KeyIndex>, String> {
+/// Built-in keyboards available for `(keyboard ...)`
+pub const KEYBOARDS: &[&str] = &["imperial44"];
+
+/// Parses a key name, unknown names get a "did you mean" hint
+pub fn parse_key(name: &str, span: Span) -> Result<Key, Error> {
+    name.parse().map_err(|_| {
+        Error::new(span, format!("Unknown key {:?}", name))
+            .with_code(Code::UnknownKey)
+            .with_suggestion(name, Key::NAMES.iter().copied())
+    })
+}
+
+pub fn parse_keymap(lst: &Vec<Expr>) -> Result<HashMap<Key, KeyIndex>, Error> {
     lst.iter()
         .enumerate()
         .try_fold(HashMap::with_capacity(lst.len()), |mut acc, (i, expr)| {
-            let key: Key = expr
-                .atom()?
-                .parse()
-                .map_err(|_| format!("Unknown key {}", expr))?;
-            if acc
-                .insert(key, i.try_into().map_err(|_| format!("Parse error"))?)
-                .is_some()
-            {
-                Err(format!("Key {:?} duplicate", key))
+            let key = parse_key(expr.atom()?, expr.span())?;
+            let index = i
+                .try_into()
+                .map_err(|_| Error::new(expr.span(), "Too many keys"))?;
+            if acc.insert(key, index).is_some() {
+                Err(Error::new(expr.span(), format!("Key {:?} duplicate", key)))
             } else {
                 Ok(acc)
             }
@@ -35,23 +44,43 @@ pub fn parse_keymap(lst: &Vec<Expr>) -> Result<HashMap<Key, KeyIndex>, String> {
 }
 
 pub fn parse(keyboard: &str) -> Result<Keyboard, String> {
-    let content = match keyboard {
-        "imperial44" => include_str!("keyboards/imperial44.rkl"),
-        _ => return Err(format!("Keyboard {:?} not found", keyboard)),
-    };
+    let (raw, meta) = split(file(keyboard)?);
+    parse_content(raw, meta).map_err(|e| e.render(&format!("{}.rkl", keyboard), raw))
+}
 
-    let (raw, meta) = if let Some((before, after)) = content.split_once("---") {
+/// S-expression part of a built-in keyboard description (without meta)
+pub fn definition(keyboard: &str) -> Result<&'static str, String> {
+    Ok(split(file(keyboard)?).0)
+}
+
+fn file(keyboard: &str) -> Result<&'static str, String> {
+    match keyboard {
+        "imperial44" => Ok(include_str!("keyboards/imperial44.rkl")),
+        _ => Err(format!("Keyboard {:?} not found", keyboard)),
+    }
+}
+
+fn split(content: &str) -> (&str, &str) {
+    if let Some((before, after)) = content.split_once("---") {
         (after.trim(), before.trim())
     } else {
         (content, "")
+    }
+}
+
+fn parse_content(raw: &str, meta: &str) -> Result<Keyboard, Error> {
+    let value =
+        s_expression::from_str_list(raw).map_err(|errors| Error::from(errors[0].clone()))?;
+    let mut keyboard = Keyboard {
+        meta: meta.to_string(),
+        ..Default::default()
     };
-    let raw = format!("({})", raw);
-    let value = s_expression::from_str(raw.as_str()).map_err(|_| "Parse error".to_string())?;
-    let mut keyboard = Keyboard::default();
-    keyboard.meta = meta.to_string();
     value.list()?.iter().try_for_each(|i| {
         let lst = i.list()?;
-        let fun = lst.first().ok_or("Expected name")?.atom()?;
+        let fun = lst
+            .first()
+            .ok_or(Error::new(i.span(), "Expected name"))?
+            .atom()?;
         match fun {
             "defmatrix" => {
                 keyboard.matrix = parse_matix(&lst[1..])?;
@@ -65,7 +94,7 @@ pub fn parse(keyboard: &str) -> Result<Keyboard, String> {
                 keyboard.source = parse_keymap(&lst[1..].to_vec())?;
                 Ok(())
             }
-            _ => Err(format!("Unexpected {}", fun)),
+            _ => Err(Error::new(i.span(), format!("Unexpected {}", fun))),
         }
     })?;
     Ok(keyboard)
@@ -1,7 +1,7 @@
 use keys::keys::Key;
-use s_expression::Expr;
+use s_expression::{Error, Expr};
+use std::collections::HashMap;
 
-#[allow(unused)]
 #[rustfmt::skip]
 #[derive(Debug)]
 pub struct Item {
@@ -10,10 +10,46 @@ pub struct Item {
     r: f32, rx: f32, ry: f32
 }
 
-#[allow(unused)]
 #[derive(Debug, Default)]
 pub struct Matrix(Vec<Item>);
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+impl Item {
+    /// Center of the key after the rotation by `r` degrees around `(rx, ry)`
+    fn center(&self) -> (f32, f32) {
+        let (x, y) = (self.x + self.w / 2.0 - self.rx, self.y + self.h / 2.0 - self.ry);
+        let (sin, cos) = self.r.to_radians().sin_cos();
+        (self.rx + x * cos - y * sin, self.ry + x * sin + y * cos)
+    }
+}
+
+impl Matrix {
+    /// Hand of every key: keys left of the middle of the keyboard are
+    /// pressed by the left hand
+    pub fn hands(&self) -> HashMap<Key, Hand> {
+        let xs = self.0.iter().map(|item| item.center().0);
+        let min = xs.clone().fold(f32::INFINITY, f32::min);
+        let max = xs.fold(f32::NEG_INFINITY, f32::max);
+        let middle = (min + max) / 2.0;
+        self.0
+            .iter()
+            .map(|item| {
+                let hand = if item.center().0 < middle {
+                    Hand::Left
+                } else {
+                    Hand::Right
+                };
+                (item.key, hand)
+            })
+            .collect()
+    }
+}
+
 fn parse_item(row: &[&str]) -> Result<Item, String> {
     match row.len() {
         5 => {
@@ -52,13 +88,31 @@ fn parse_item(row: &[&str]) -> Result<Item, String> {
     }
 }
 
-pub fn parse<'a>(items: &[Expr<'a>]) -> Result<Matrix, String> {
+pub fn parse<'a>(items: &[Expr<'a>]) -> Result<Matrix, Error> {
     let mut matrix: Vec<Item> = Vec::with_capacity(items.len());
     items.iter().try_for_each(|x| {
         let row = x.list()?.iter().filter_map(|el| el.atom().ok());
         let row: Vec<&str> = row.collect();
-        matrix.push(parse_item(row.as_slice())?);
-        Ok::<(), String>(())
+        matrix.push(parse_item(row.as_slice()).map_err(|e| Error::new(x.span(), e))?);
+        Ok::<(), Error>(())
     })?;
     Ok(Matrix(matrix))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hands_from_geometry() {
+        let hands = crate::parse("imperial44").unwrap().matrix.hands();
+        for (key, hand) in [
+            (Key::A, Hand::Left),
+            (Key::G, Hand::Left),
+            (Key::H, Hand::Right),
+            (Key::Semicolon, Hand::Right),
+        ] {
+            assert_eq!(hands[&key], hand, "{:?}", key);
+        }
+    }
+}
@@ -1,5 +1,5 @@
-use keys::keys::{Key, KeyIndex};
-use s_expression::{Expr, Expr::*};
+use keys::keys::KeyIndex;
+use s_expression::{Error, Expr, Expr::*};
 use std::collections::HashMap;
 
 #[derive(Debug)]
@@ -16,7 +16,7 @@ impl Vial {
         self.0.insert(index, item)
     }
     pub fn ok_or<E>(&self, e: E) -> Result<&HashMap<KeyIndex, Item>, E> {
-        if self.0.len() > 0 {
+        if !self.0.is_empty() {
             Ok(&self.0)
         } else {
             Err(e)
@@ -24,22 +24,21 @@ impl Vial {
     }
 }
 
-pub fn parse<'a>(items: &[Expr<'a>]) -> Result<Vial, String> {
+pub fn parse<'a>(items: &[Expr<'a>]) -> Result<Vial, Error> {
     let mut vial = Vial(HashMap::new());
     items.iter().enumerate().try_for_each(|(i, x)| {
+        let err = |message: String| Error::new(x.span(), message);
         let row = x.list()?.iter().filter_map(|e| match e {
-            Atom(s) => Some(*s),
+            Atom(s, _) => Some(*s),
             _ => None,
         });
         let row: Vec<&str> = row.collect();
-        let first = row.first().ok_or("Key not found".to_string())?;
-        let key: Key = first
-            .parse()
-            .map_err(|_| format!("Unknown key {}", first))?;
+        let first = row.first().ok_or(err("Key not found".to_string()))?;
+        let key = crate::parse_key(first, x.span())?;
         let item = match row.as_slice() {
             [a, b] | [a, b, _] => {
-                let a = a.parse().map_err(|_| format!("Unknown value {}", a))?;
-                let b = b.parse().map_err(|_| format!("Unknown value {}", b))?;
+                let a = a.parse().map_err(|_| err(format!("Unknown value {}", a)))?;
+                let b = b.parse().map_err(|_| err(format!("Unknown value {}", b)))?;
 
                 Some(match row.get(2) {
                     Some(&"e") => Item::Encoder(a, b),
@@ -50,11 +49,12 @@ pub fn parse<'a>(items: &[Expr<'a>]) -> Result<Vial, String> {
         };
 
         match vial.insert(
-            i.try_into().map_err(|_| format!("Invalid index {}", i))?,
-            item.ok_or(format!("Unexpected {:?}", row))?,
+            i.try_into()
+                .map_err(|_| err(format!("Invalid index {}", i)))?,
+            item.ok_or(err(format!("Unexpected {:?}", row)))?,
         ) {
             None => {}
-            _ => return Err(format!("Key {:?} already in map", key)),
+            _ => return Err(err(format!("Key {:?} already in map", key))),
         }
         Ok(())
     })?;
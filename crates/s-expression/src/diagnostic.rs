@@ -0,0 +1,264 @@
+use crate::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Stable identifier of a diagnostic kind, printed as `error[E0002]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Code {
+    Syntax,
+    UnknownKey,
+    UnknownLayer,
+    UnknownAlias,
+    UnknownAction,
+    UnknownKeyboard,
+    Include,
+    Unsupported,
+    LayerCycle,
+    AliasCycle,
+    UnknownOption,
+    UnusedAlias,
+    UnusedTemplate,
+    UnusedLayer,
+    OverwrittenEntry,
+    LayerTrap,
+    UnreachableLayer,
+}
+
+impl Code {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Code::Syntax => "E0001",
+            Code::UnknownKey => "E0002",
+            Code::UnknownLayer => "E0003",
+            Code::UnknownAlias => "E0004",
+            Code::UnknownAction => "E0005",
+            Code::UnknownKeyboard => "E0006",
+            Code::Include => "E0007",
+            Code::Unsupported => "E0008",
+            Code::LayerCycle => "E0009",
+            Code::AliasCycle => "E0010",
+            Code::UnknownOption => "E0011",
+            Code::UnusedAlias => "W0001",
+            Code::UnusedTemplate => "W0002",
+            Code::UnusedLayer => "W0003",
+            Code::OverwrittenEntry => "W0004",
+            Code::LayerTrap => "W0005",
+            Code::UnreachableLayer => "W0006",
+        }
+    }
+}
+
+impl std::fmt::Display for Code {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+/// Error or warning with an optional location in the source text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<Code>,
+    pub message: String,
+    pub span: Option<Span>,
+    pub notes: Vec<String>,
+    pub help: Option<String>,
+}
+
+pub type Error = Diagnostic;
+
+impl Diagnostic {
+    pub fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span: Some(span),
+            ..message.into().into()
+        }
+    }
+
+    pub fn warning(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::new(span, message)
+        }
+    }
+
+    pub fn with_code(mut self, code: Code) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    /// Adds "did you mean" help with the candidate closest to `name`
+    pub fn with_suggestion<'a>(
+        self,
+        name: &str,
+        candidates: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        match closest(name, candidates) {
+            Some(candidate) => self.with_help(format!("did you mean `{}`?", candidate)),
+            None => self,
+        }
+    }
+
+    /// Attaches `span` unless the error already points somewhere more precise
+    pub fn or_at(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// Formats the diagnostic as `file:line:col` followed by the source line
+    /// with the span underlined, notes and help
+    pub fn render(&self, file: &str, source: &str) -> String {
+        let title = format!(
+            "{}{}: {}",
+            match self.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            },
+            self.code.map(|c| format!("[{}]", c)).unwrap_or_default(),
+            self.message
+        );
+        let Some(span) = self.span else {
+            return format!("{}\n --> {}{}", title, file, self.footer(" "));
+        };
+        let (line, col) = span.line_col(source);
+        let text = source.lines().nth(line - 1).unwrap_or_default();
+        let width = source[span.start.min(source.len())..span.end.min(source.len())]
+            .split('\n')
+            .next()
+            .map_or(1, |s| s.chars().count().max(1));
+        let number = line.to_string();
+        let pad = " ".repeat(number.len());
+        // Keep tabs so the carets line up with the source line
+        let indent: String = text
+            .chars()
+            .take(col - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        format!(
+            "{title}\n{pad}--> {file}:{line}:{col}\n{pad} |\n{number} | {text}\n{pad} | {indent}{carets}{footer}",
+            carets = "^".repeat(width),
+            footer = self.footer(&pad),
+        )
+    }
+
+    fn footer(&self, pad: &str) -> String {
+        self.notes
+            .iter()
+            .map(|note| format!("\n{} = note: {}", pad, note))
+            .chain(self.help.iter().map(|help| format!("\n{} = help: {}", pad, help)))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.message.fmt(f)
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+impl From<String> for Diagnostic {
+    fn from(message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            code: None,
+            message,
+            span: None,
+            notes: Vec::new(),
+            help: None,
+        }
+    }
+}
+
+impl From<&str> for Diagnostic {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+/// Candidate within a small edit distance of `name`, the closest one wins
+pub fn closest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|c| *c != name)
+        .map(|c| (distance(name, c), c))
+        .filter(|(d, _)| *d <= limit)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/// Levenshtein distance, case-insensitive
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(row[j + 1])
+            };
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggestions() {
+        assert_eq!(closest("bkss", ["bks", "esc", "bspc"]), Some("bks"));
+        assert_eq!(closest("defaultt", ["default", "nav"]), Some("default"));
+        assert_eq!(closest("xyz", ["default", "nav"]), None);
+        assert_eq!(closest("a", ["a"]), None);
+    }
+
+    #[test]
+    fn render() {
+        let source = "(deflayer x\n    bkss)";
+        let error = Diagnostic::new(Span::new(16, 20), "Unknown key \"bkss\"")
+            .with_code(Code::UnknownKey)
+            .with_note("keys are defined in defsrc")
+            .with_suggestion("bkss", ["bks"]);
+        assert_eq!(
+            error.render("a.rkl", source),
+            "error[E0002]: Unknown key \"bkss\"\n --> a.rkl:2:5\n  |\n2 |     bkss)\n  |     ^^^^\n  = note: keys are defined in defsrc\n  = help: did you mean `bks`?"
+        );
+        let warning = Diagnostic::from("Unused").with_help("remove it");
+        assert_eq!(
+            Diagnostic {
+                severity: Severity::Warning,
+                ..warning
+            }
+            .render("a.rkl", source),
+            "warning: Unused\n --> a.rkl\n  = help: remove it"
+        );
+    }
+}
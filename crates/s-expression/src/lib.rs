@@ -1,9 +1,37 @@
+mod diagnostic;
 mod parse;
+mod span;
+mod syntax;
 mod tokenize;
 
-pub use parse::Expr;
+pub use diagnostic::{Code, Diagnostic, Error, Severity, closest};
+pub use parse::{Expr, ParseError, Parser};
+pub use span::Span;
+pub use syntax::{Document, Node, NodeKind, parse_lossless};
 
-pub fn from_str<'a>(input: &'a str) -> Result<parse::Expr<'a>, ()> {
-    let mut tokens = tokenize::tokenize(input);
-    return parse::parse(&mut tokens);
+/// Parses a single expression, reporting the first syntax error
+pub fn from_str(input: &str) -> Result<Expr<'_>, ParseError> {
+    let mut parser = Parser::new(input);
+    let expr = parser.parse();
+    match (parser.errors.first(), expr) {
+        (Some(e), _) => Err(e.clone()),
+        (None, Some(expr)) => Ok(expr),
+        (None, None) => Err(parser.eof()),
+    }
+}
+
+/// Parses every top-level expression of `input` into one list spanning the
+/// whole text, so spans stay relative to `input`. All syntax errors are
+/// reported at once
+pub fn from_str_list(input: &str) -> Result<Expr<'_>, Vec<ParseError>> {
+    let mut parser = Parser::new(input);
+    let mut list = Vec::new();
+    while let Some(expr) = parser.parse() {
+        list.push(expr);
+    }
+    if parser.errors.is_empty() {
+        Ok(Expr::List(list, Span::new(0, input.len())))
+    } else {
+        Err(parser.errors)
+    }
 }
@@ -1,37 +1,96 @@
-#[derive(Debug, Clone, PartialEq, Eq)]
+use crate::{
+    Code, Error, Span,
+    tokenize::{Token, TokenKind, tokenize},
+};
+
+#[derive(Clone)]
 pub enum Expr<'a> {
-    Atom(&'a str),
-    List(Vec<Expr<'a>>),
+    Atom(&'a str, Span),
+    /// Quoted string literal with escapes applied
+    Str(String, Span),
+    List(Vec<Expr<'a>>, Span),
 }
 impl Default for Expr<'static> {
     fn default() -> Self {
-        Self::List(vec![])
+        Self::List(vec![], Span::default())
     }
 }
 
 impl<'a> Expr<'a> {
-    pub fn list(&self) -> Result<&Vec<Expr<'a>>, String> {
+    pub fn list(&self) -> Result<&Vec<Expr<'a>>, Error> {
+        match self {
+            Expr::List(list, _) => Ok(list),
+            _ => Err(Error::new(
+                self.span(),
+                format!("Expected list, found {}", self),
+            )),
+        }
+    }
+    pub fn atom(&self) -> Result<&'a str, Error> {
         match self {
-            Expr::List(list) => Ok(list),
-            _ => return Err(format!("Expected list, found {:?}", self)),
+            Expr::Atom(s, _) => Ok(*s),
+            _ => Err(Error::new(
+                self.span(),
+                format!("Expected atom, found {}", self),
+            )),
         }
     }
-    pub fn atom(&self) -> Result<&'a str, String> {
+    pub fn string(&self) -> Result<&str, Error> {
         match self {
-            Expr::Atom(s) => Ok(*s),
-            _ => return Err(format!("Expected atom, found {:?}", self)),
+            Expr::Str(s, _) => Ok(s),
+            _ => Err(Error::new(
+                self.span(),
+                format!("Expected string, found {}", self),
+            )),
+        }
+    }
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Atom(_, span) | Expr::Str(_, span) | Expr::List(_, span) => *span,
+        }
+    }
+}
+
+/// Expressions are compared structurally, spans are ignored
+impl PartialEq for Expr<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Expr::Atom(a, _), Expr::Atom(b, _)) => a == b,
+            (Expr::Str(a, _), Expr::Str(b, _)) => a == b,
+            (Expr::List(a, _), Expr::List(b, _)) => a == b,
+            _ => false,
         }
     }
-    pub fn to_string(&self) -> String {
-        format!("{}", self)
+}
+impl Eq for Expr<'_> {}
+
+impl std::fmt::Debug for Expr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Atom(x, _) => f.debug_tuple("Atom").field(x).finish(),
+            Self::Str(x, _) => f.debug_tuple("Str").field(x).finish(),
+            Self::List(lst, _) => f.debug_tuple("List").field(lst).finish(),
+        }
     }
 }
 
 impl std::fmt::Display for Expr<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Atom(x) => write!(f, "{}", x),
-            Self::List(lst) => {
+            Self::Atom(x, _) => write!(f, "{}", x),
+            Self::Str(x, _) => {
+                write!(f, "\"")?;
+                x.chars().try_for_each(|c| match c {
+                    '"' => write!(f, "\\\""),
+                    '\\' => write!(f, "\\\\"),
+                    '\n' => write!(f, "\\n"),
+                    '\t' => write!(f, "\\t"),
+                    c if c.is_control() => write!(f, "\\u{{{:x}}}", c as u32),
+                    c => write!(f, "{}", c),
+                })?;
+                write!(f, "\"")
+            }
+            Self::List(lst, _) => {
                 write!(f, "(")?;
                 lst.iter().enumerate().try_for_each(|(i, e)| {
                     if i != 0 {
@@ -45,22 +104,249 @@ impl std::fmt::Display for Expr<'_> {
     }
 }
 
-pub fn parse<'a>(tokens: &mut Vec<&'a str>) -> Result<Expr<'a>, ()> {
-    let token = tokens.remove(0);
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// Input ended where an expression was expected
+    UnexpectedEof(Span),
+    /// `)` without a matching `(`
+    UnexpectedClose(Span),
+    /// List opened at the span was never closed
+    Unclosed(Span),
+    /// String opened at the span was never closed
+    UnterminatedString(Span),
+    /// Unknown or malformed escape sequence in a string
+    InvalidEscape(Span),
+    /// Block comment opened at the span was never closed
+    UnterminatedComment(Span),
+    /// `#;` not followed by an expression
+    MissingDatum(Span),
+}
+
+impl ParseError {
+    pub fn span(&self) -> Span {
+        match self {
+            Self::UnexpectedEof(span)
+            | Self::UnexpectedClose(span)
+            | Self::Unclosed(span)
+            | Self::UnterminatedString(span)
+            | Self::InvalidEscape(span)
+            | Self::UnterminatedComment(span)
+            | Self::MissingDatum(span) => *span,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnexpectedEof(_) => write!(f, "Unexpected end of input"),
+            Self::UnexpectedClose(_) => write!(f, "Unexpected ')'"),
+            Self::Unclosed(_) => write!(f, "Unclosed list, expected ')'"),
+            Self::UnterminatedString(_) => write!(f, "Unterminated string, expected '\"'"),
+            Self::InvalidEscape(_) => write!(
+                f,
+                "Invalid escape, expected one of \\\", \\\\, \\n, \\t or \\u{{...}}"
+            ),
+            Self::UnterminatedComment(_) => write!(f, "Unterminated comment, expected '|#'"),
+            Self::MissingDatum(_) => write!(f, "Expected expression after '#;'"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<ParseError> for Error {
+    fn from(e: ParseError) -> Self {
+        Error::new(e.span(), e.to_string()).with_code(Code::Syntax)
+    }
+}
+
+/// Recursive descent parser that records errors and keeps going: stray `)`
+/// are skipped and unclosed lists are closed at the end of input
+pub struct Parser<'a> {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token<'a>>>,
+    end: usize,
+    pub errors: Vec<ParseError>,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(input: &'a str) -> Self {
+        let (mut tokens, errors) = tokenize(input);
+        tokens.retain(|t| t.kind != TokenKind::Comment);
+        Self {
+            tokens: tokens.into_iter().peekable(),
+            end: input.len(),
+            errors,
+        }
+    }
+
+    /// Next expression, `None` once the input is exhausted
+    pub fn parse(&mut self) -> Option<Expr<'a>> {
+        loop {
+            self.tokens.peek()?;
+            if let Some(expr) = self.item() {
+                return Some(expr);
+            }
+        }
+    }
+
+    /// Consumes one token (or a whole list), `None` when it yields no
+    /// expression: a comment or a stray `)`
+    fn item(&mut self) -> Option<Expr<'a>> {
+        let token = self.tokens.next()?;
+        match token.kind {
+            TokenKind::Open => Some(self.list(token.span)),
+            TokenKind::Close => {
+                self.errors.push(ParseError::UnexpectedClose(token.span));
+                None
+            }
+            TokenKind::Atom => Some(Expr::Atom(token.text, token.span)),
+            TokenKind::Str(value) => Some(Expr::Str(value, token.span)),
+            TokenKind::DatumComment => {
+                if self.datum().is_none() {
+                    self.errors.push(ParseError::MissingDatum(token.span));
+                }
+                None
+            }
+            TokenKind::Comment => None,
+        }
+    }
+
+    /// Next expression inside the current list, stops before `)`
+    fn datum(&mut self) -> Option<Expr<'a>> {
+        loop {
+            match self.tokens.peek() {
+                None => return None,
+                Some(token) if token.kind == TokenKind::Close => return None,
+                Some(_) => {
+                    if let Some(expr) = self.item() {
+                        return Some(expr);
+                    }
+                }
+            }
+        }
+    }
 
-    match token {
-        "(" => {
-            let mut list = Vec::new();
-            while tokens[0] != ")" {
-                match parse(tokens) {
-                    Ok(t) => list.push(t),
-                    Err(error) => return Err(error),
+    fn list(&mut self, open: Span) -> Expr<'a> {
+        let mut list = Vec::new();
+        loop {
+            match self.tokens.peek() {
+                None => {
+                    self.errors.push(ParseError::Unclosed(open));
+                    return Expr::List(list, open.join(Span::new(self.end, self.end)));
+                }
+                Some(token) if token.kind == TokenKind::Close => {
+                    let close = token.span;
+                    self.tokens.next();
+                    return Expr::List(list, open.join(close));
                 }
+                Some(_) => list.extend(self.item()),
             }
-            tokens.remove(0); // ')'
-            Ok(Expr::List(list))
         }
-        ")" => Err(()),
-        _ => Ok(Expr::Atom(token)),
+    }
+
+    /// Error for an input that ended before any expression
+    pub fn eof(&self) -> ParseError {
+        ParseError::UnexpectedEof(Span::new(self.end, self.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ParseError, Span, from_str, from_str_list};
+
+    #[test]
+    fn unclosed_list() {
+        assert_eq!(
+            from_str("(a (b c)").unwrap_err(),
+            ParseError::Unclosed(Span::new(0, 1))
+        );
+    }
+
+    #[test]
+    fn stray_close() {
+        assert_eq!(
+            from_str(")").unwrap_err(),
+            ParseError::UnexpectedClose(Span::new(0, 1))
+        );
+    }
+
+    #[test]
+    fn empty_input() {
+        assert_eq!(
+            from_str("  ;; comment").unwrap_err(),
+            ParseError::UnexpectedEof(Span::new(12, 12))
+        );
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_eq!(
+            from_str(r#"("a b" "\"(\\)\n" "\u{44f}")"#).unwrap(),
+            from_str(r#"("a b" "\"(\\)\n" "я")"#).unwrap(),
+        );
+        let expr = from_str(r#""\"(\\)\n""#).unwrap();
+        assert_eq!(expr.string().unwrap(), "\"(\\)\n");
+        assert_eq!(expr.to_string(), r#""\"(\\)\n""#);
+    }
+
+    #[test]
+    fn quote_inside_atom() {
+        assert_eq!(from_str(r#"."x"#).unwrap().atom().unwrap(), r#"."x"#);
+    }
+
+    #[test]
+    fn string_errors() {
+        assert_eq!(
+            from_str(r#"("\q" "abc)"#).unwrap_err(),
+            ParseError::InvalidEscape(Span::new(2, 4))
+        );
+        assert_eq!(
+            from_str_list(r#"("\u{110000}" "abc)"#).unwrap_err(),
+            vec![
+                ParseError::InvalidEscape(Span::new(2, 12)),
+                ParseError::UnterminatedString(Span::new(14, 15)),
+                ParseError::Unclosed(Span::new(0, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            from_str("(a #| b #| nested |# (c |# d #;(e f) g #; h)").unwrap(),
+            from_str("(a d g)").unwrap(),
+        );
+        assert_eq!(
+            from_str("(a #;#;b c d)").unwrap(),
+            from_str("(a d)").unwrap(),
+        );
+        assert_eq!(
+            from_str("(; .# #a |#)").unwrap().to_string(),
+            "(; .# #a |#)"
+        );
+    }
+
+    #[test]
+    fn comment_errors() {
+        assert_eq!(
+            from_str_list("(a #;) #| b").unwrap_err(),
+            vec![
+                ParseError::UnterminatedComment(Span::new(7, 9)),
+                ParseError::MissingDatum(Span::new(3, 5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn several_errors() {
+        assert_eq!(
+            from_str_list("(a)) (b (c)\n(d e))) (f").unwrap_err(),
+            vec![
+                ParseError::UnexpectedClose(Span::new(3, 4)),
+                ParseError::UnexpectedClose(Span::new(18, 19)),
+                ParseError::Unclosed(Span::new(20, 21)),
+            ]
+        );
     }
 }
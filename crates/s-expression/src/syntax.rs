@@ -0,0 +1,190 @@
+use crate::{
+    ParseError, Parser, Span,
+    tokenize::{Token, TokenKind, tokenize},
+};
+
+/// Node of the lossless syntax tree. Unlike [`crate::Expr`] it keeps
+/// comments and whitespace, so the source can be reproduced byte for byte
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node<'a> {
+    /// Whitespace between the previous token and this node
+    pub leading: &'a str,
+    pub kind: NodeKind<'a>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeKind<'a> {
+    /// Atom or string literal as written
+    Atom(&'a str),
+    /// `;;` line comment or `#| ... |#` block comment
+    Comment(&'a str),
+    /// `#;` and the expression it disables
+    DatumComment(Box<Node<'a>>),
+    /// Children and the whitespace before `)`
+    List(Vec<Node<'a>>, &'a str),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document<'a> {
+    pub nodes: Vec<Node<'a>>,
+    /// Whitespace after the last node
+    pub trailing: &'a str,
+}
+
+impl<'a> Node<'a> {
+    pub fn atom(&self) -> Option<&'a str> {
+        match self.kind {
+            NodeKind::Atom(s) => Some(s),
+            _ => None,
+        }
+    }
+    pub fn list(&self) -> Option<&[Node<'a>]> {
+        match &self.kind {
+            NodeKind::List(list, _) => Some(list),
+            _ => None,
+        }
+    }
+    pub fn is_comment(&self) -> bool {
+        matches!(self.kind, NodeKind::Comment(_) | NodeKind::DatumComment(_))
+    }
+    /// `;;` comment, the next token has to start on a new line
+    pub fn is_line_comment(&self) -> bool {
+        matches!(self.kind, NodeKind::Comment(s) if s.starts_with(";;"))
+    }
+    /// Number of line breaks before the node
+    pub fn newlines(&self) -> usize {
+        self.leading.matches('\n').count()
+    }
+}
+
+impl std::fmt::Display for Node<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.leading)?;
+        match &self.kind {
+            NodeKind::Atom(s) | NodeKind::Comment(s) => write!(f, "{}", s),
+            NodeKind::DatumComment(node) => write!(f, "#;{}", node),
+            NodeKind::List(list, trailing) => {
+                write!(f, "(")?;
+                list.iter().try_for_each(|n| n.fmt(f))?;
+                write!(f, "{})", trailing)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Document<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.nodes.iter().try_for_each(|n| n.fmt(f))?;
+        write!(f, "{}", self.trailing)
+    }
+}
+
+/// Parses `input` into a lossless tree, syntax errors are reported the same
+/// way as by [`crate::from_str_list`]
+pub fn parse_lossless(input: &str) -> Result<Document<'_>, Vec<ParseError>> {
+    let mut parser = Parser::new(input);
+    while parser.parse().is_some() {}
+    if !parser.errors.is_empty() {
+        return Err(parser.errors);
+    }
+
+    let (tokens, _) = tokenize(input);
+    let mut builder = Builder {
+        input,
+        tokens: tokens.into_iter().peekable(),
+        end: 0,
+    };
+    let mut nodes = Vec::new();
+    while let Some(node) = builder.node() {
+        nodes.push(node);
+    }
+    Ok(Document {
+        nodes,
+        trailing: &input[builder.end..],
+    })
+}
+
+struct Builder<'a> {
+    input: &'a str,
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token<'a>>>,
+    /// End of the last consumed token
+    end: usize,
+}
+
+impl<'a> Builder<'a> {
+    fn leading(&mut self, token: &Token) -> &'a str {
+        let leading = &self.input[self.end..token.span.start];
+        self.end = token.span.end;
+        leading
+    }
+
+    /// Input is already checked, so lists are balanced here
+    fn node(&mut self) -> Option<Node<'a>> {
+        let token = self.tokens.next()?;
+        let leading = self.leading(&token);
+        let kind = match token.kind {
+            TokenKind::Atom | TokenKind::Str(_) => NodeKind::Atom(token.text),
+            TokenKind::Comment => NodeKind::Comment(token.text),
+            TokenKind::DatumComment => NodeKind::DatumComment(Box::new(self.node()?)),
+            TokenKind::Open => {
+                let mut list = Vec::new();
+                while self
+                    .tokens
+                    .peek()
+                    .is_some_and(|t| t.kind != TokenKind::Close)
+                {
+                    list.extend(self.node());
+                }
+                let close = self.tokens.next()?;
+                let trailing = self.leading(&close);
+                return Some(Node {
+                    leading,
+                    kind: NodeKind::List(list, trailing),
+                    span: token.span.join(close.span),
+                });
+            }
+            TokenKind::Close => return None,
+        };
+        let span = match &kind {
+            NodeKind::DatumComment(node) => token.span.join(node.span),
+            _ => token.span,
+        };
+        Some(Node {
+            leading,
+            kind,
+            span,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lossless() {
+        let input = r#"
+;; header
+(defsrc a   b
+    c ;)
+#| block #| nested |# |#
+(deflayer x #;(tap-hold a M) "\"" .; c)  ;; tail
+"#;
+        let document = parse_lossless(input).unwrap();
+        assert_eq!(document.to_string(), input);
+        assert_eq!(document.nodes.len(), 5);
+        assert!(document.nodes[0].is_line_comment());
+        assert_eq!(document.nodes[1].list().unwrap()[4].atom(), Some(";"));
+        assert!(document.nodes[2].is_comment() && !document.nodes[2].is_line_comment());
+        assert!(document.nodes[4].is_line_comment());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            parse_lossless("(a").unwrap_err(),
+            vec![ParseError::Unclosed(Span::new(0, 1))]
+        );
+    }
+}
@@ -1,30 +1,59 @@
-pub fn tokenize(input: &str) -> Vec<&str> {
+use crate::{ParseError, Span};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    Open,
+    Close,
+    Atom,
+    /// Quoted string with escapes already applied
+    Str(String),
+    /// `#;` that comments out the next expression
+    DatumComment,
+    /// `;;` line comment or `#| ... |#` block comment
+    Comment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+impl<'a> Token<'a> {
+    fn new(kind: TokenKind, input: &'a str, start: usize, end: usize) -> Self {
+        Self {
+            kind,
+            text: &input[start..end],
+            span: Span::new(start, end),
+        }
+    }
+}
+
+pub fn tokenize(input: &str) -> (Vec<Token<'_>>, Vec<ParseError>) {
     let mut tokens = Vec::new();
+    let mut errors = Vec::new();
     let mut start = None;
-    let mut in_comment = false;
 
     let mut iter = input.char_indices().peekable();
 
     while let Some((i, c)) = iter.next() {
-        // Если мы внутри комментария — пропускаем всё до конца строки
-        if in_comment {
-            if c == '\n' {
-                in_comment = false;
-            }
-            continue;
-        }
-
         match c {
             ';' => {
                 if let Some(&(_, ';')) = iter.peek() {
                     iter.next(); // съедаем второй ';'
 
                     if let Some(s) = start {
-                        tokens.push(&input[s..i]);
+                        tokens.push(Token::new(TokenKind::Atom, input, s, i));
                         start = None;
                     }
 
-                    in_comment = true;
+                    // комментарий продолжается до конца строки
+                    let mut end = i + 2;
+                    while let Some((j, c)) = iter.next_if(|&(_, c)| c != '\n') {
+                        end = j + c.len_utf8();
+                    }
+                    tokens.push(Token::new(TokenKind::Comment, input, i, end));
                 } else {
                     // одиночный ; считаем символом
                     if start.is_none() {
@@ -33,17 +62,38 @@ pub fn tokenize(input: &str) -> Vec<&str> {
                 }
             }
 
+            // `#|` и `#;` — комментарии только в начале токена
+            '#' if start.is_none() && matches!(iter.peek(), Some((_, '|' | ';'))) => {
+                if let Some((_, '|')) = iter.next() {
+                    let end = block_comment(input, i, &mut iter, &mut errors);
+                    tokens.push(Token::new(TokenKind::Comment, input, i, end));
+                } else {
+                    tokens.push(Token::new(TokenKind::DatumComment, input, i, i + 2));
+                }
+            }
+
+            // кавычка внутри атома (например `."`) остается символом
+            '"' if start.is_none() => {
+                let (value, end) = string(input, i, &mut iter, &mut errors);
+                tokens.push(Token::new(TokenKind::Str(value), input, i, end));
+            }
+
             '(' | ')' => {
                 if let Some(s) = start {
-                    tokens.push(&input[s..i]);
+                    tokens.push(Token::new(TokenKind::Atom, input, s, i));
                     start = None;
                 }
-                tokens.push(&input[i..i + c.len_utf8()]);
+                let kind = if c == '(' {
+                    TokenKind::Open
+                } else {
+                    TokenKind::Close
+                };
+                tokens.push(Token::new(kind, input, i, i + c.len_utf8()));
             }
 
             c if c.is_whitespace() => {
                 if let Some(s) = start {
-                    tokens.push(&input[s..i]);
+                    tokens.push(Token::new(TokenKind::Atom, input, s, i));
                     start = None;
                 }
             }
@@ -57,8 +107,94 @@ pub fn tokenize(input: &str) -> Vec<&str> {
     }
 
     if let Some(s) = start {
-        tokens.push(&input[s..]);
+        tokens.push(Token::new(TokenKind::Atom, input, s, input.len()));
     }
 
-    tokens
+    (tokens, errors)
+}
+
+/// Skips a (possibly nested) `#| ... |#` comment after the opening `#|` at
+/// `open`, returns the end of the comment
+fn block_comment(
+    input: &str,
+    open: usize,
+    iter: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+    errors: &mut Vec<ParseError>,
+) -> usize {
+    let mut depth = 1;
+    while let Some((i, c)) = iter.next() {
+        match c {
+            '#' if iter.next_if(|&(_, c)| c == '|').is_some() => depth += 1,
+            '|' if iter.next_if(|&(_, c)| c == '#').is_some() => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 2;
+                }
+            }
+            _ => {}
+        }
+    }
+    errors.push(ParseError::UnterminatedComment(Span::new(open, open + 2)));
+    input.len()
+}
+
+/// Reads a string literal after the opening quote at `open`, returns the
+/// unescaped value and the end of the literal
+fn string(
+    input: &str,
+    open: usize,
+    iter: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+    errors: &mut Vec<ParseError>,
+) -> (String, usize) {
+    let mut value = String::new();
+    while let Some((i, c)) = iter.next() {
+        match c {
+            '"' => return (value, i + 1),
+            '\\' => match escape(input, i, iter) {
+                Ok(c) => value.push(c),
+                Err(e) => errors.push(e),
+            },
+            c => value.push(c),
+        }
+    }
+    errors.push(ParseError::UnterminatedString(Span::new(open, open + 1)));
+    (value, input.len())
+}
+
+fn escape(
+    input: &str,
+    backslash: usize,
+    iter: &mut std::iter::Peekable<std::str::CharIndices<'_>>,
+) -> Result<char, ParseError> {
+    let invalid = |end: usize| ParseError::InvalidEscape(Span::new(backslash, end));
+    let Some((i, c)) = iter.next() else {
+        return Err(invalid(input.len()));
+    };
+    let end = i + c.len_utf8();
+    Ok(match c {
+        '"' => '"',
+        '\\' => '\\',
+        'n' => '\n',
+        't' => '\t',
+        'u' => {
+            if iter.next_if(|&(_, c)| c == '{').is_none() {
+                return Err(invalid(end));
+            }
+            let mut end = end + 1;
+            let mut code = String::new();
+            while let Some((i, c)) = iter.next_if(|&(_, c)| c != '"') {
+                end = i + c.len_utf8();
+                if c == '}' {
+                    return u32::from_str_radix(&code, 16)
+                        .ok()
+                        .filter(|_| (1..=6).contains(&code.len()))
+                        .and_then(char::from_u32)
+                        .ok_or(invalid(end));
+                }
+                code.push(c);
+            }
+            return Err(invalid(end));
+        }
+        _ => return Err(invalid(end)),
+    })
 }
@@ -0,0 +1,174 @@
+use super::{GAP, INDENT, head, header, single_line, width};
+use s_expression::{Node, NodeKind, parse_lossless};
+
+/// Key positions of `defsrc`: every key gets a column, columns keep the
+/// relative placement of the keys in the source
+pub struct Grid {
+    /// Column of every key
+    columns: Vec<usize>,
+    /// Keys of every row
+    rows: Vec<Vec<usize>>,
+    /// Room between neighbour columns in the source
+    room: Vec<usize>,
+}
+
+/// `defsrc`, `defvial` or `deflayer` with one item per grid key
+pub struct Body {
+    header: String,
+    items: Vec<String>,
+    /// Comments on their own line before the key
+    before: Vec<(usize, String)>,
+    /// Comments at the end of the line of the key
+    after: Vec<(usize, String)>,
+}
+
+impl Grid {
+    /// Grid of the file's `defsrc` or of the built-in keyboard it uses
+    pub fn new(nodes: &[Node], source: &str) -> Option<Self> {
+        if let Some(defsrc) = nodes.iter().find(|n| head(n) == Some("defsrc")) {
+            return Self::from_defsrc(defsrc, source);
+        }
+        let keyboard = nodes.iter().find(|n| head(n) == Some("keyboard"))?;
+        let definition = parser::definition(keyboard.list()?.get(1)?.atom()?).ok()?;
+        let document = parse_lossless(definition).ok()?;
+        let defsrc = document.nodes.iter().find(|n| head(n) == Some("defsrc"))?;
+        Self::from_defsrc(defsrc, definition)
+    }
+
+    fn from_defsrc(defsrc: &Node, source: &str) -> Option<Self> {
+        let keys = defsrc
+            .list()?
+            .iter()
+            .skip(1)
+            .filter(|n| !n.is_line_comment())
+            .map(|n| n.atom().map(|_| n.span.line_col(source)))
+            .collect::<Option<Vec<_>>>()?;
+        if keys.is_empty() {
+            return None;
+        }
+
+        // Keys written right after `(defsrc` are moved to the indentation of
+        // the other rows
+        let (open_line, _) = defsrc.span.line_col(source);
+        let first = keys.iter().find(|(line, _)| *line == open_line);
+        let indent = keys
+            .iter()
+            .filter(|(line, _)| *line != open_line)
+            .map(|(_, col)| *col)
+            .min();
+        let shift = |&(line, col): &(usize, usize)| match (first, indent) {
+            (Some((_, first)), Some(indent)) if line == open_line => col - first + indent,
+            (Some((_, first)), None) => col - first,
+            _ => col,
+        };
+        let offsets: Vec<usize> = keys.iter().map(shift).collect();
+        let mut positions = offsets.clone();
+        positions.sort();
+        positions.dedup();
+
+        let columns = offsets
+            .iter()
+            .map(|o| positions.binary_search(o).unwrap_or_default())
+            .collect();
+        let mut rows: Vec<Vec<usize>> = Vec::new();
+        for (i, (line, _)) in keys.iter().enumerate() {
+            match rows.last_mut() {
+                Some(row) if keys[row[0]].0 == *line => row.push(i),
+                _ => rows.push(vec![i]),
+            }
+        }
+        let mut room: Vec<usize> = positions.windows(2).map(|w| w[1] - w[0] - 1).collect();
+        room.push(0);
+        Some(Self {
+            columns,
+            rows,
+            room,
+        })
+    }
+
+    /// Splits a grid form into items, `None` if it doesn't fit the grid
+    pub fn body(&self, node: &Node) -> Option<Body> {
+        let children = node.list()?;
+        let len = match head(node)? {
+            "defsrc" | "defvial" => 1,
+            "deflayer" => 2,
+            _ => return None,
+        };
+        let mut body = Body {
+            header: header(children, len)?,
+            items: Vec::new(),
+            before: Vec::new(),
+            after: Vec::new(),
+        };
+        for child in &children[len..] {
+            match &child.kind {
+                NodeKind::Comment(s) if child.is_line_comment() => {
+                    let index = body.items.len();
+                    if child.newlines() == 0 && index > 0 {
+                        body.after.push((index - 1, s.to_string()));
+                    } else {
+                        body.before.push((index, s.to_string()));
+                    }
+                }
+                _ => body.items.push(single_line(child, INDENT)?),
+            }
+        }
+        (body.items.len() == self.columns.len()).then_some(body)
+    }
+
+    /// Column widths fitting the source gaps and all the items
+    pub fn widths<'a>(&self, bodies: impl Iterator<Item = &'a Body>) -> Vec<usize> {
+        let mut widths = self.room.clone();
+        for body in bodies {
+            for (item, &column) in body.items.iter().zip(&self.columns) {
+                widths[column] = widths[column].max(width(item));
+            }
+        }
+        widths
+    }
+
+    pub fn render(&self, body: &Body, widths: &[usize]) -> String {
+        let starts: Vec<usize> = widths
+            .iter()
+            .scan(0, |start, w| {
+                let current = *start;
+                *start += w + 1;
+                Some(current)
+            })
+            .collect();
+        let comments = |list: &[(usize, String)], from: usize, to: usize| {
+            list.iter()
+                .filter(move |(i, _)| (from..to).contains(i))
+                .map(|(_, s)| s.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let mut lines = Vec::new();
+        for row in &self.rows {
+            let (from, to) = (row[0], row[row.len() - 1] + 1);
+            for comment in comments(&body.before, from, to) {
+                lines.push(format!("{}{}", INDENT, comment));
+            }
+            let mut line = INDENT.to_string();
+            let mut cursor = 0;
+            for &key in row {
+                let start = starts[self.columns[key]];
+                line.push_str(&" ".repeat(start.saturating_sub(cursor)));
+                line.push_str(&body.items[key]);
+                cursor = start.max(cursor) + width(&body.items[key]);
+            }
+            let mut after = comments(&body.after, from, to).into_iter();
+            if let Some(comment) = after.next() {
+                line.push_str(GAP);
+                line.push_str(&comment);
+            }
+            lines.push(line);
+            lines.extend(after.map(|comment| format!("{}{}", INDENT, comment)));
+        }
+        let end = body.items.len();
+        for comment in comments(&body.before, end, end + 1) {
+            lines.push(format!("{}{}", INDENT, comment));
+        }
+        format!("{}\n{}\n)", body.header, lines.join("\n"))
+    }
+}
@@ -0,0 +1,174 @@
+use s_expression::{Error, Node, NodeKind, parse_lossless};
+
+mod grid;
+mod pairs;
+use grid::Grid;
+
+const INDENT: &str = "    ";
+/// Space between a row and its trailing comment, and between pairs
+const GAP: &str = "    ";
+
+/// Canonical formatting of a layout file. Comments are kept, `defsrc`,
+/// `defvial` and `deflayer` are aligned to the `defsrc` grid (the file's own
+/// or the one of its `keyboard`), `defalias`, `deflayermap` and `defoverride`
+/// pairs are aligned as a table. Formatting is idempotent
+pub fn format(content: &str) -> Result<String, Vec<Error>> {
+    let document = parse_lossless(content)
+        .map_err(|errors| errors.into_iter().map(Error::from).collect::<Vec<_>>())?;
+    let grid = Grid::new(&document.nodes, content);
+    let bodies: Vec<_> = document
+        .nodes
+        .iter()
+        .map(|node| grid.as_ref().and_then(|grid| grid.body(node)))
+        .collect();
+    let widths = grid
+        .as_ref()
+        .map(|grid| grid.widths(bodies.iter().flatten()))
+        .unwrap_or_default();
+
+    let mut out = String::new();
+    for (i, (node, body)) in document.nodes.iter().zip(&bodies).enumerate() {
+        if i > 0 {
+            out.push_str(&separator(node, &document.nodes[i - 1], ""));
+        }
+        match (&grid, body) {
+            (Some(grid), Some(body)) => out.push_str(&grid.render(body, &widths)),
+            _ => out.push_str(&pairs::render(node).unwrap_or_else(|| generic(node, ""))),
+        }
+    }
+    if !document.nodes.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Whitespace before `node`: the original line breaks (at most one blank line)
+/// or a single space
+fn separator(node: &Node, prev: &Node, indent: &str) -> String {
+    if node.newlines() == 0 && !prev.is_line_comment() {
+        " ".to_string()
+    } else {
+        format!("{}{}", "\n".repeat(node.newlines().clamp(1, 2)), indent)
+    }
+}
+
+/// Formats `node` keeping its line breaks, nested lines are indented relative
+/// to `indent`
+fn generic(node: &Node, indent: &str) -> String {
+    match &node.kind {
+        NodeKind::Atom(s) | NodeKind::Comment(s) => s.to_string(),
+        NodeKind::DatumComment(node) => format!("#;{}", generic(node, indent)),
+        NodeKind::List(children, trailing) => {
+            let inner = format!("{}{}", indent, INDENT);
+            let mut out = String::from("(");
+            for (i, child) in children.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(&separator(child, &children[i - 1], &inner));
+                } else if child.newlines() > 0 {
+                    out.push('\n');
+                    out.push_str(&inner);
+                }
+                out.push_str(&generic(child, &inner));
+            }
+            if trailing.contains('\n') || children.last().is_some_and(Node::is_line_comment) {
+                out.push('\n');
+                out.push_str(indent);
+            }
+            out.push(')');
+            out
+        }
+    }
+}
+
+fn head<'a>(node: &Node<'a>) -> Option<&'a str> {
+    node.list()?.first()?.atom()
+}
+
+/// `(name arg...` of a form, written on one line
+fn header(children: &[Node], len: usize) -> Option<String> {
+    let items = children
+        .get(..len)?
+        .iter()
+        .map(|node| single_line(node, INDENT))
+        .collect::<Option<Vec<_>>>()?;
+    Some(format!("({}", items.join(" ")))
+}
+
+/// Formatted element of a table, comments and multi-line expressions can't be
+/// aligned
+fn single_line(node: &Node, indent: &str) -> Option<String> {
+    if node.is_comment() {
+        return None;
+    }
+    let text = generic(node, indent);
+    (!text.contains('\n')).then_some(text)
+}
+
+fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(input: &str, expected: &str) {
+        let output = format(input).unwrap();
+        assert_eq!(output, expected);
+        assert_eq!(format(&output).unwrap(), output, "not idempotent");
+        assert_eq!(
+            s_expression::from_str_list(input).unwrap(),
+            s_expression::from_str_list(&output).unwrap()
+        );
+    }
+
+    #[test]
+    fn generic_forms() {
+        check(
+            ";; layout\n\n\n(keyboard   imperial44)  ;; board\n(deftemplate    (t x)\n  (multi x\n x)\n  )\n#;  (unwrap a)",
+            ";; layout\n\n(keyboard imperial44) ;; board\n(deftemplate (t x)\n    (multi x\n        x)\n)\n#;(unwrap a)\n",
+        );
+    }
+
+    #[test]
+    fn layers_aligned_to_defsrc() {
+        check(
+            "(defsrc\n  esc q w\n  tab a s)\n(deflayer base\n (multi C q) 1 2 ;; top\n\n ;; bottom\n 3 4 5\n)\n(deflayer short a b)",
+            "(defsrc\n    esc         q w\n    tab         a s\n)\n(deflayer base\n    (multi C q) 1 2    ;; top\n    ;; bottom\n    3           4 5\n)\n(deflayer short a b)\n",
+        );
+    }
+
+    #[test]
+    fn grid_keeps_gaps() {
+        check(
+            "(defsrc a b     c d\n          e f)\n(deflayer x 1 2 3 4 5 6)",
+            "(defsrc\n    a b     c d\n    e f\n)\n(deflayer x\n    1 2     3 4\n    5 6\n)\n",
+        );
+    }
+
+    #[test]
+    fn builtin_keyboard_grid() {
+        let keys = vec!["_"; 48].join(" ");
+        let output = format(&format!("(keyboard imperial44)\n(deflayer x {})", keys)).unwrap();
+        assert_eq!(output.lines().count(), 7);
+        assert!(output.contains("\n    _   _ _ _ _ _                    _ _ _ _ _ _\n"));
+        assert!(output.contains("\n              _ _ _   _     _    _   _ _\n)"));
+        assert_eq!(format(&output).unwrap(), output);
+    }
+
+    #[test]
+    fn pairs_aligned() {
+        check(
+            "(defalias a0 (app 0) a1 (app 1)\n  num (layer-while-held num))\n(deflayermap (base)\n  caps esc ;; escape\n  a (tap-hold a M) ; (tap-hold ; M))",
+            "(defalias\n    a0  (app 0)    a1 (app 1)\n    num (layer-while-held num)\n)\n(deflayermap (base)\n    caps esc    ;; escape\n    a    (tap-hold a M)    ; (tap-hold ; M)\n)\n",
+        );
+    }
+
+    #[test]
+    fn unalignable_pairs() {
+        check(
+            "(defalias a #| b |# c)\n(defalias a)",
+            "(defalias a #| b |# c)\n(defalias a)\n",
+        );
+    }
+}
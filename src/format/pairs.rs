@@ -0,0 +1,90 @@
+use super::{GAP, INDENT, head, header, single_line, width};
+use s_expression::{Node, NodeKind};
+
+enum Row {
+    /// Key-value pairs written on one line and the comment after them
+    Pairs(Vec<(String, String)>, Option<String>),
+    Comment(String),
+}
+
+/// `defalias`, `deflayermap` and `defoverride` with pairs aligned as a table,
+/// `None` if the form can't be aligned
+pub fn render(node: &Node) -> Option<String> {
+    let children = node.list()?;
+    let len = match head(node)? {
+        "defalias" => 1,
+        "deflayermap" | "defoverride" => 2,
+        _ => return None,
+    };
+    let header = header(children, len)?;
+
+    let mut rows: Vec<Row> = Vec::new();
+    let mut key: Option<String> = None;
+    for child in &children[len..] {
+        match (&child.kind, key.take()) {
+            (NodeKind::Comment(s), None) if child.is_line_comment() => match rows.last_mut() {
+                Some(Row::Pairs(_, comment @ None)) if child.newlines() == 0 => {
+                    *comment = Some(s.to_string())
+                }
+                _ => rows.push(Row::Comment(s.to_string())),
+            },
+            (_, None) => {
+                if child.newlines() > 0 || !matches!(rows.last(), Some(Row::Pairs(_, None))) {
+                    rows.push(Row::Pairs(Vec::new(), None));
+                }
+                key = Some(single_line(child, INDENT)?);
+            }
+            (_, Some(k)) => {
+                let value = single_line(child, INDENT)?;
+                if let Some(Row::Pairs(pairs, _)) = rows.last_mut() {
+                    pairs.push((k, value));
+                }
+            }
+        }
+    }
+    if key.is_some() || rows.is_empty() {
+        return None;
+    }
+
+    let mut key_widths: Vec<usize> = Vec::new();
+    let mut value_widths: Vec<usize> = Vec::new();
+    for row in &rows {
+        if let Row::Pairs(pairs, _) = row {
+            for (i, (k, v)) in pairs.iter().enumerate() {
+                if key_widths.len() <= i {
+                    key_widths.push(0);
+                    value_widths.push(0);
+                }
+                key_widths[i] = key_widths[i].max(width(k));
+                if i + 1 < pairs.len() {
+                    value_widths[i] = value_widths[i].max(width(v));
+                }
+            }
+        }
+    }
+
+    let lines: Vec<String> = rows
+        .iter()
+        .map(|row| match row {
+            Row::Comment(comment) => format!("{}{}", INDENT, comment),
+            Row::Pairs(pairs, comment) => {
+                let mut line = INDENT.to_string();
+                for (i, (k, v)) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        line.push_str(GAP);
+                    }
+                    line.push_str(&format!("{:w$} {}", k, v, w = key_widths[i]));
+                    if i + 1 < pairs.len() {
+                        line.push_str(&" ".repeat(value_widths[i] - width(v)));
+                    }
+                }
+                if let Some(comment) = comment {
+                    line.push_str(GAP);
+                    line.push_str(comment);
+                }
+                line
+            }
+        })
+        .collect();
+    Some(format!("{}\n{}\n)", header, lines.join("\n")))
+}
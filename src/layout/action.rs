@@ -1,18 +1,231 @@
 use std::collections::HashMap;
 
-use keys::keys::Key;
-use s_expression::Expr::{self, *};
+use super::options::{ms, options as keyword_options};
+use keys::{keys::Key, mods::Mods};
+use s_expression::{
+    Code, Error,
+    Expr::{self, *},
+    Span,
+};
+use std::fmt;
+
+/// Actions activating the layer given as their first argument
+pub const LAYER_ACTIONS: &[&str] = &[
+    "layer-while-held",
+    "layer-switch",
+    "layer-toggle",
+    "layer-to",
+    "layer-tap-toggle",
+    "layer-with-mods",
+];
+
+/// Names of the list actions, `(name ...)`
+const ACTIONS: &[&str] = &[
+    "tap-hold",
+    "home-row-mod",
+    "tap-dance",
+    "tap-dance-eager",
+    "one-shot",
+    "multi",
+    "layer-while-held",
+    "layer-switch",
+    "layer-toggle",
+    "layer-to",
+    "layer-tap-toggle",
+    "layer-with-mods",
+    "mouse-move",
+    "mouse-wheel",
+    "macro",
+    "seq",
+];
+
+/// Keyword options of `tap-hold`, `:name value`
+const TAP_HOLD_OPTIONS: &[&str] = &[":timeout", ":tap-timeout", ":flavor", ":tap-keys"];
+
+/// How `tap-hold` decides on hold before its timeout
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Flavor {
+    /// Hold only after the timeout
+    #[default]
+    Timeout,
+    /// Hold as soon as another key is pressed
+    Press,
+    /// Hold when another key is pressed and released (permissive hold)
+    Release,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TapHoldOptions {
+    /// Time to hold the key until the hold action, ms
+    pub timeout: Option<u16>,
+    /// Time after a tap in which pressing again holds the tap action, ms
+    pub tap_timeout: Option<u16>,
+    pub flavor: Flavor,
+    /// Keys that choose the tap action when pressed before the decision,
+    /// usually the keys of the same hand. Implies [`Flavor::Release`]
+    pub tap_keys: Vec<Key>,
+    /// Set by `home-row-mod`: the keys of the same hand are added to
+    /// `tap_keys` once the key position is known
+    pub bilateral: bool,
+}
+
+/// Keyword options of `tap-dance`
+const TAP_DANCE_OPTIONS: &[&str] = &[":tap", ":hold", ":double-tap", ":tap-hold", ":term"];
+
+/// Branches of `tap-dance`, a missing branch does nothing
+#[derive(Debug, Clone, Default)]
+pub struct TapDance {
+    pub tap: Option<Box<Action>>,
+    pub hold: Option<Box<Action>>,
+    pub double_tap: Option<Box<Action>>,
+    /// Tap, then press and hold
+    pub tap_hold: Option<Box<Action>>,
+    /// Time to wait for the next tap or the hold, ms
+    pub term: Option<u16>,
+    /// `tap-dance-eager`: every tap runs its action right away
+    pub eager: bool,
+}
+
+impl TapDance {
+    pub fn branches(&self) -> impl Iterator<Item = &Action> {
+        [&self.tap, &self.hold, &self.double_tap, &self.tap_hold]
+            .into_iter()
+            .flatten()
+            .map(|a| a.as_ref())
+    }
+
+    fn branches_mut(&mut self) -> impl Iterator<Item = &mut Box<Action>> {
+        [
+            &mut self.tap,
+            &mut self.hold,
+            &mut self.double_tap,
+            &mut self.tap_hold,
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// Keyword options of `one-shot`
+const ONE_SHOT_OPTIONS: &[&str] = &[":timeout", ":end", ":pause-on-layer"];
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OneShotOptions {
+    /// Time the one-shot waits for the next key, ms
+    pub timeout: Option<u16>,
+    /// Ends on the release of the next key instead of its press
+    pub on_release: bool,
+    /// Activating the one-shot doesn't consume the active ones, so a
+    /// one-shot layer keeps a one-shot modifier pressed before it
+    pub pause_on_layer: bool,
+    /// The `one-shot` form, its target is checked once aliases are resolved
+    pub span: Span,
+}
+
+/// Keyword options of `mouse-move`, `mouse-wheel` takes all but `:accel`
+const MOUSE_OPTIONS: &[&str] = &[":speed", ":interval", ":accel"];
+
+/// Direction of `mouse-move` and `mouse-wheel`, the wheel has no diagonals
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    UpLeft,
+    UpRight,
+    DownLeft,
+    DownRight,
+}
+
+impl Direction {
+    const NAMES: &[&str] = &[
+        "up",
+        "down",
+        "left",
+        "right",
+        "up-left",
+        "up-right",
+        "down-left",
+        "down-right",
+    ];
+
+    fn from_name(name: &str) -> Option<Direction> {
+        Some(match name {
+            "up" => Direction::Up,
+            "down" => Direction::Down,
+            "left" => Direction::Left,
+            "right" => Direction::Right,
+            "up-left" => Direction::UpLeft,
+            "up-right" => Direction::UpRight,
+            "down-left" => Direction::DownLeft,
+            "down-right" => Direction::DownRight,
+            _ => return None,
+        })
+    }
+
+    pub fn name(&self) -> &'static str {
+        Self::NAMES[*self as usize]
+    }
+
+    /// Straight directions making up the direction, two for a diagonal
+    pub fn parts(&self) -> Vec<Direction> {
+        match self {
+            Direction::UpLeft => vec![Direction::Up, Direction::Left],
+            Direction::UpRight => vec![Direction::Up, Direction::Right],
+            Direction::DownLeft => vec![Direction::Down, Direction::Left],
+            Direction::DownRight => vec![Direction::Down, Direction::Right],
+            d => vec![*d],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MouseOptions {
+    /// Distance per interval, pixels for the cursor and 1/120 of a notch
+    /// for the wheel
+    pub speed: Option<u16>,
+    /// Time between two movements, ms
+    pub interval: Option<u16>,
+    /// Time to accelerate from 1 to `speed`, ms
+    pub accel: Option<u16>,
+}
 
 #[derive(Debug, Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Action {
     Tap(Key),
     Transparent,
     NoAction,
     Alias(String),
-    TapHold(Box<Action>, Box<Action>),
+    TapHold(Box<Action>, Box<Action>, TapHoldOptions),
+    TapDance(TapDance),
+    /// Modifiers or `layer-while-held` applied to the next key only
+    OneShot(Box<Action>, OneShotOptions),
     Multi(Vec<Action>),
     LayerWhileHeld(String),
     LayerSwitch(String),
+    /// Turns the layer on or off until toggled again (TG)
+    LayerToggle(String),
+    /// Turns the layer on and every other layer off (TO)
+    LayerTo(String),
+    /// Layer while held, toggled by a tap (TT)
+    LayerTapToggle(String),
+    /// Layer while held with modifiers (LM)
+    LayerWithMods(String, Mods),
+    /// Shifts letters until a key other than a letter, a digit or one of
+    /// `caps-word-keys` is pressed
+    CapsWord,
+    /// Repeats the last key
+    Repeat,
+    /// Alternate of the last key, e.g. `pgdn` after `pgup`
+    AltRepeat,
+    /// Starts a sequence of `defseq`
+    Leader,
+    /// Moves the mouse cursor while held
+    MouseMove(Direction, MouseOptions),
+    /// Scrolls the mouse wheel while held
+    MouseWheel(Direction, MouseOptions),
     Unicode(char),
     Sequence(Vec<Action>),
     #[allow(dead_code)]
@@ -22,35 +235,98 @@ pub enum Action {
 }
 
 impl Action {
-    pub fn resolve_aliases(&self, aliases: &HashMap<String, Action>) -> Result<Action, String> {
-        let res = match self {
-            Action::Alias(name) => aliases
-                .get(name)
-                .map(|a| a.resolve_aliases(aliases))
-                .ok_or(format!("Alias @{} not found", name))?,
-            Action::TapHold(tap, hold) => Ok(Action::TapHold(
-                Box::new(tap.resolve_aliases(aliases)?),
-                Box::new(hold.resolve_aliases(aliases)?),
-            )),
-            Action::Multi(actions) => Ok(Action::Multi(
-                actions
-                    .iter()
-                    .map(|a| a.resolve_aliases(aliases))
-                    .collect::<Result<_, _>>()?,
-            )),
-            _ => Ok(self.clone()),
+    /// Rebuilds the action with `f` applied to every directly nested action
+    pub fn try_map<E>(
+        &self,
+        f: &mut impl FnMut(&Action) -> Result<Action, E>,
+    ) -> Result<Action, E> {
+        Ok(match self {
+            Action::TapHold(tap, hold, options) => {
+                Action::TapHold(Box::new(f(tap)?), Box::new(f(hold)?), options.clone())
+            }
+            Action::OneShot(action, options) => {
+                Action::OneShot(Box::new(f(action)?), options.clone())
+            }
+            Action::TapDance(dance) => {
+                let mut dance = dance.clone();
+                for branch in dance.branches_mut() {
+                    **branch = f(branch)?;
+                }
+                Action::TapDance(dance)
+            }
+            Action::Multi(actions) => {
+                Action::Multi(actions.iter().map(&mut *f).collect::<Result<_, _>>()?)
+            }
+            Action::Sequence(actions) => {
+                Action::Sequence(actions.iter().map(&mut *f).collect::<Result<_, _>>()?)
+            }
+            Action::Tap(_)
+            | Action::Transparent
+            | Action::NoAction
+            | Action::Alias(_)
+            | Action::LayerWhileHeld(_)
+            | Action::LayerSwitch(_)
+            | Action::LayerToggle(_)
+            | Action::LayerTo(_)
+            | Action::LayerTapToggle(_)
+            | Action::LayerWithMods(..)
+            | Action::CapsWord
+            | Action::Repeat
+            | Action::AltRepeat
+            | Action::Leader
+            | Action::MouseMove(..)
+            | Action::MouseWheel(..)
+            | Action::Unicode(_)
+            | Action::Hold(_)
+            | Action::Release(_) => self.clone(),
+        })
+    }
+
+    /// Replaces every alias, however deep, with its definition
+    pub fn resolve_aliases(&self, aliases: &HashMap<String, Action>) -> Result<Action, Error> {
+        self.resolve_aliases_in(aliases, &mut Vec::new())
+    }
+
+    fn resolve_aliases_in(
+        &self,
+        aliases: &HashMap<String, Action>,
+        chain: &mut Vec<String>,
+    ) -> Result<Action, Error> {
+        let Action::Alias(name) = self else {
+            return self.try_map(&mut |a| a.resolve_aliases_in(aliases, chain));
         };
-        return res;
+        if let Some(start) = chain.iter().position(|n| n == name) {
+            let cycle: Vec<_> = chain[start..]
+                .iter()
+                .chain([name])
+                .map(|n| format!("@{}", n))
+                .collect();
+            return Err(Error::from(format!("Alias cycle: {}", cycle.join(" -> ")))
+                .with_code(Code::AliasCycle));
+        }
+        let action = aliases.get(name).ok_or_else(|| {
+            Error::from(format!("Alias @{} not found", name)).with_code(Code::UnknownAlias)
+        })?;
+        chain.push(name.clone());
+        let action = action.resolve_aliases_in(aliases, chain)?;
+        chain.pop();
+        Ok(action)
     }
 
+    /// Layers active on top of the current ones while the key is held
     pub fn layer_while_held_names(&self) -> Vec<&str> {
         match self {
-            Action::LayerWhileHeld(name) => vec![name.as_str()],
-            Action::TapHold(tap, hold) => {
+            Action::LayerWhileHeld(name) | Action::LayerWithMods(name, _) => vec![name.as_str()],
+            Action::OneShot(action, _) => action.layer_while_held_names(),
+            Action::TapHold(tap, hold, _) => {
                 let mut v = tap.layer_while_held_names();
                 v.extend(hold.layer_while_held_names());
                 v
             }
+            Action::TapDance(dance) => dance
+                .branches()
+                .flat_map(|a| a.layer_while_held_names())
+                .collect(),
             Action::Multi(actions) | Action::Sequence(actions) => actions
                 .iter()
                 .flat_map(|a| a.layer_while_held_names())
@@ -59,10 +335,73 @@ impl Action {
         }
     }
 
+    /// Layers latched on top of the current ones until toggled off
+    pub fn layer_toggle_names(&self) -> Vec<&str> {
+        match self {
+            Action::LayerToggle(name) | Action::LayerTapToggle(name) => vec![name.as_str()],
+            Action::OneShot(action, _) => action.layer_toggle_names(),
+            Action::TapHold(tap, hold, _) => {
+                let mut v = tap.layer_toggle_names();
+                v.extend(hold.layer_toggle_names());
+                v
+            }
+            Action::TapDance(dance) => dance
+                .branches()
+                .flat_map(|a| a.layer_toggle_names())
+                .collect(),
+            Action::Multi(actions) | Action::Sequence(actions) => actions
+                .iter()
+                .flat_map(|a| a.layer_toggle_names())
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Layers replacing the active ones
+    pub fn layer_switch_names(&self) -> Vec<&str> {
+        match self {
+            Action::LayerSwitch(name) | Action::LayerTo(name) => vec![name.as_str()],
+            Action::TapHold(tap, hold, _) => {
+                let mut v = tap.layer_switch_names();
+                v.extend(hold.layer_switch_names());
+                v
+            }
+            Action::TapDance(dance) => dance
+                .branches()
+                .flat_map(|a| a.layer_switch_names())
+                .collect(),
+            Action::Multi(actions) | Action::Sequence(actions) => actions
+                .iter()
+                .flat_map(|a| a.layer_switch_names())
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Aliases referenced by the action, their definitions are not followed
+    pub fn alias_names(&self) -> Vec<&str> {
+        match self {
+            Action::Alias(name) => vec![name.as_str()],
+            Action::OneShot(action, _) => action.alias_names(),
+            Action::TapHold(tap, hold, _) => {
+                let mut v = tap.alias_names();
+                v.extend(hold.alias_names());
+                v
+            }
+            Action::TapDance(dance) => dance.branches().flat_map(|a| a.alias_names()).collect(),
+            Action::Multi(actions) | Action::Sequence(actions) => {
+                actions.iter().flat_map(|a| a.alias_names()).collect()
+            }
+            _ => vec![],
+        }
+    }
+
     pub fn contains_unicode(&self) -> bool {
         match self {
             Action::Unicode(_) => true,
-            Action::TapHold(tap, hold) => tap.contains_unicode() || hold.contains_unicode(),
+            Action::TapHold(tap, hold, _) => tap.contains_unicode() || hold.contains_unicode(),
+            Action::TapDance(dance) => dance.branches().any(|a| a.contains_unicode()),
+            Action::OneShot(action, _) => action.contains_unicode(),
             Action::Multi(actions) | Action::Sequence(actions) => {
                 actions.iter().any(|a| a.contains_unicode())
             }
@@ -70,17 +409,57 @@ impl Action {
         }
     }
 
+    /// Checks that one-shots hold modifiers or `layer-while-held`, aliases
+    /// must be resolved first
+    pub fn check_one_shots(&self) -> Result<(), Error> {
+        match self {
+            Action::OneShot(target, options) => {
+                let is_mod = |a: &Action| matches!(a, Action::Tap(k) if k.is_modifier());
+                let valid = match target.as_ref() {
+                    Action::Multi(actions) => actions.iter().all(is_mod),
+                    Action::LayerWhileHeld(_) => true,
+                    a => is_mod(a),
+                };
+                if !valid {
+                    return Err(Error::new(
+                        options.span,
+                        format!("Expected modifiers or layer-while-held, found {}", target),
+                    ));
+                }
+                Ok(())
+            }
+            Action::TapHold(tap, hold, _) => {
+                tap.check_one_shots()?;
+                hold.check_one_shots()
+            }
+            Action::TapDance(dance) => dance.branches().try_for_each(|a| a.check_one_shots()),
+            Action::Multi(actions) | Action::Sequence(actions) => {
+                actions.iter().try_for_each(|a| a.check_one_shots())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Renames the layers of [`Action::layer_while_held_names`] and
+    /// [`Action::layer_toggle_names`]
     pub fn map_layer_while_held(&mut self, f: &impl Fn(&str) -> Option<String>) {
         match self {
-            Action::LayerWhileHeld(name) => {
+            Action::LayerWhileHeld(name)
+            | Action::LayerToggle(name)
+            | Action::LayerTapToggle(name)
+            | Action::LayerWithMods(name, _) => {
                 if let Some(new) = f(name) {
                     *name = new;
                 }
             }
-            Action::TapHold(tap, hold) => {
+            Action::TapHold(tap, hold, _) => {
                 tap.map_layer_while_held(f);
                 hold.map_layer_while_held(f);
             }
+            Action::OneShot(action, _) => action.map_layer_while_held(f),
+            Action::TapDance(dance) => {
+                dance.branches_mut().for_each(|a| a.map_layer_while_held(f));
+            }
             Action::Multi(actions) | Action::Sequence(actions) => {
                 actions.iter_mut().for_each(|a| a.map_layer_while_held(f));
             }
@@ -88,33 +467,60 @@ impl Action {
         }
     }
 
-    pub fn from_expr(expr: &Expr) -> Result<Action, String> {
+    /// Actions written as a plain name
+    fn from_name(name: &str) -> Option<Action> {
+        Some(match name {
+            "caps-word" => Action::CapsWord,
+            "repeat" => Action::Repeat,
+            "alt-repeat" => Action::AltRepeat,
+            "leader" => Action::Leader,
+            _ => return None,
+        })
+    }
+
+    /// Action typing `c`; whitespace, ASCII letters and digits map to keys,
+    /// the rest is resolved through the keymap unicode tables
+    fn from_char(c: char) -> Action {
+        let key = |c: char| c.to_string().parse().map(Action::Tap);
+        match c {
+            ' ' => Action::Tap(Key::Space),
+            '\n' => Action::Tap(Key::Enter),
+            '\t' => Action::Tap(Key::Tab),
+            c if c.is_ascii_uppercase() => match key(c.to_ascii_lowercase()) {
+                Ok(tap) => Action::Multi(vec![Action::Tap(Key::LeftShift), tap]),
+                Err(_) => Action::Unicode(c),
+            },
+            c if c.is_ascii_alphanumeric() => key(c).unwrap_or(Action::Unicode(c)),
+            c => Action::Unicode(c),
+        }
+    }
+
+    pub fn from_expr(expr: &Expr) -> Result<Action, Error> {
         Ok(match expr {
-            Atom(e) => {
+            Atom(e, span) => {
+                let span = *span;
                 if let Some(d) = e.strip_prefix(".")
                     && !d.is_empty()
                 {
                     Self::Unicode(
                         d.chars()
                             .next()
-                            .ok_or("Unicode prefix '.' requires a character".to_string())?,
+                            .ok_or(Error::new(span, "Unicode prefix '.' requires a character"))?,
                     )
                 } else if let Some(d) = e.strip_prefix("@")
                     && e.len() > 1
                 {
                     Action::Alias(d.to_string())
+                } else if let Some(action) = Self::from_name(e) {
+                    action
                 } else if let keys = e.split("-")
                     && !e.ends_with("-")
                     && !e.starts_with("-")
                     && e.matches("-").count() > 0
                 {
                     Action::Multi(
-                        keys.map(|key| {
-                            key.parse()
-                                .map(Action::Tap)
-                                .map_err(|_| format!("Unknown key {:?} at {}", key, expr))
-                        })
-                        .collect::<Result<_, _>>()?,
+                        keys.map(|key| parser::parse_key(key, span).map(Action::Tap))
+                            .collect::<Result<_, _>>()?,
                     )
                 } else {
                     match *e {
@@ -122,52 +528,424 @@ impl Action {
                         "_" => Action::Transparent,
                         "lb" => Self::Unicode('('),
                         "rb" => Self::Unicode(')'),
-                        k => Action::Tap(k.parse().map_err(|_| format!("Unknown key {:?}", k))?),
+                        k => Action::Tap(parser::parse_key(k, span)?),
                     }
                 }
             }
-            List(list) => {
-                let [Atom(name), params @ ..] = list.as_slice() else {
-                    return Err(format!("Unknown action {}", expr));
+            Str(text, span) => {
+                let mut actions: Vec<_> = text.chars().map(Self::from_char).collect();
+                match actions.len() {
+                    0 => return Err(Error::new(*span, "Empty string")),
+                    1 => actions.remove(0),
+                    _ => Action::Sequence(actions),
+                }
+            }
+            List(list, span) => {
+                let syntax_error = || Error::new(*span, "Syntax error");
+                let [Atom(name, name_span), params @ ..] = list.as_slice() else {
+                    return Err(Error::new(*span, format!("Unknown action {}", expr))
+                        .with_code(Code::UnknownAction));
                 };
                 match *name {
                     "tap-hold" => {
-                        let [tap, hold] = params else {
-                            return Err(format!("Syntax error"));
+                        let [tap, hold, options @ ..] = params else {
+                            return Err(syntax_error());
                         };
                         let tap = Self::from_expr(tap)?;
                         let hold = Self::from_expr(hold)?;
-                        Action::TapHold(Box::new(tap), Box::new(hold))
+                        let options = tap_hold_options(options)?;
+                        Action::TapHold(Box::new(tap), Box::new(hold), options)
+                    }
+                    "home-row-mod" => {
+                        let [tap, hold, options @ ..] = params else {
+                            return Err(syntax_error());
+                        };
+                        let tap = Self::from_expr(tap)?;
+                        let hold = Self::from_expr(hold)?;
+                        let mut options = tap_hold_options(options)?;
+                        if options.flavor == Flavor::Press {
+                            return Err(Error::new(
+                                *span,
+                                "home-row-mod can't be used with the press flavor",
+                            )
+                            .with_note("it holds only when the next key is on the other hand"));
+                        }
+                        options.flavor = Flavor::Release;
+                        options.bilateral = true;
+                        Action::TapHold(Box::new(tap), Box::new(hold), options)
+                    }
+                    "tap-dance" | "tap-dance-eager" => {
+                        let mut dance = tap_dance(params)?;
+                        if dance.branches().next().is_none() {
+                            return Err(Error::new(*span, "Expected at least one branch")
+                                .with_help("add `:tap action`"));
+                        }
+                        dance.eager = *name == "tap-dance-eager";
+                        Action::TapDance(dance)
+                    }
+                    "one-shot" => {
+                        let [action, options @ ..] = params else {
+                            return Err(syntax_error());
+                        };
+                        let target = Self::from_expr(action)?;
+                        let options = OneShotOptions {
+                            span: *span,
+                            ..one_shot_options(options)?
+                        };
+                        Action::OneShot(Box::new(target), options)
                     }
                     "multi" => {
                         let actions: Vec<Action> = params
-                            .into_iter()
-                            .map(|e| Self::from_expr(e))
+                            .iter()
+                            .map(Self::from_expr)
                             .collect::<Result<_, _>>()?;
                         Action::Multi(actions)
                     }
                     "layer-while-held" => {
-                        let [Atom(name)] = params else {
-                            return Err(format!("Syntax error"));
+                        let [Atom(name, _)] = params else {
+                            return Err(syntax_error());
                         };
                         Action::LayerWhileHeld(name.to_string())
                     }
                     "layer-switch" => {
-                        let [Atom(name)] = params else {
-                            return Err(format!("Syntax error"));
+                        let [Atom(name, _)] = params else {
+                            return Err(syntax_error());
                         };
                         Action::LayerSwitch(name.to_string())
                     }
+                    "layer-toggle" | "layer-to" | "layer-tap-toggle" => {
+                        let [Atom(layer, _)] = params else {
+                            return Err(syntax_error());
+                        };
+                        let layer = layer.to_string();
+                        match *name {
+                            "layer-toggle" => Action::LayerToggle(layer),
+                            "layer-to" => Action::LayerTo(layer),
+                            _ => Action::LayerTapToggle(layer),
+                        }
+                    }
+                    "layer-with-mods" => {
+                        let [Atom(layer, _), mods @ ..] = params else {
+                            return Err(syntax_error());
+                        };
+                        let mut keys = Mods::NONE;
+                        for m in mods {
+                            let taps = match Self::from_expr(m)? {
+                                Action::Multi(actions) => actions,
+                                action => vec![action],
+                            };
+                            for tap in taps {
+                                let modifier = match tap {
+                                    Action::Tap(key) => Mods::from_key(&key),
+                                    _ => None,
+                                };
+                                keys = keys
+                                    | modifier.ok_or_else(|| {
+                                        Error::new(
+                                            m.span(),
+                                            format!("Expected modifier, found {}", m),
+                                        )
+                                    })?;
+                            }
+                        }
+                        if keys.is_empty() {
+                            return Err(syntax_error());
+                        }
+                        Action::LayerWithMods(layer.to_string(), keys)
+                    }
+                    "mouse-move" | "mouse-wheel" => {
+                        let [Atom(direction, direction_span), options @ ..] = params else {
+                            return Err(syntax_error());
+                        };
+                        let wheel = *name == "mouse-wheel";
+                        let names = if wheel {
+                            &Direction::NAMES[..4]
+                        } else {
+                            Direction::NAMES
+                        };
+                        let direction = Direction::from_name(direction)
+                            .filter(|_| names.contains(direction))
+                            .ok_or_else(|| {
+                                Error::new(
+                                    *direction_span,
+                                    format!("Unknown direction {:?}", direction),
+                                )
+                                .with_suggestion(direction, names.iter().copied())
+                            })?;
+                        let options = mouse_options(options, wheel)?;
+                        if wheel {
+                            Action::MouseWheel(direction, options)
+                        } else {
+                            Action::MouseMove(direction, options)
+                        }
+                    }
                     "macro" | "seq" => {
                         let actions: Vec<Action> = params
-                            .into_iter()
-                            .map(|e| Self::from_expr(e))
-                            .collect::<Result<_, _>>()?;
+                            .iter()
+                            .map(|e| {
+                                Ok(match (e, Self::from_expr(e)?) {
+                                    // text inside a macro is spliced into the sequence
+                                    (Str(..), Action::Sequence(chars)) => chars,
+                                    (_, action) => vec![action],
+                                })
+                            })
+                            .collect::<Result<Vec<_>, Error>>()?
+                            .concat();
                         Action::Sequence(actions)
                     }
-                    _ => return Err(format!("Unknown action {:?}", name)),
+                    _ => {
+                        return Err(Error::new(*name_span, format!("Unknown action {:?}", name))
+                            .with_code(Code::UnknownAction)
+                            .with_suggestion(name, ACTIONS.iter().copied()));
+                    }
                 }
             }
         })
     }
 }
+
+/// Writes the action the way it is written in a layout
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |f: &mut fmt::Formatter<'_>, name: &str, actions: &[Action]| {
+            write!(f, "({}", name)?;
+            for action in actions {
+                write!(f, " {}", action)?;
+            }
+            f.write_str(")")
+        };
+        let option = |f: &mut fmt::Formatter<'_>, name: &str, value: Option<u16>| match value {
+            Some(value) => write!(f, " {} {}", name, value),
+            None => Ok(()),
+        };
+        match self {
+            Action::Tap(key) => f.write_str(&key_name(key)),
+            Action::Transparent => f.write_str("_"),
+            Action::NoAction => f.write_str("X"),
+            Action::Alias(name) => write!(f, "@{}", name),
+            Action::TapHold(tap, hold, options) => {
+                let name = if options.bilateral {
+                    "home-row-mod"
+                } else {
+                    "tap-hold"
+                };
+                write!(f, "({} {} {}", name, tap, hold)?;
+                option(f, ":timeout", options.timeout)?;
+                option(f, ":tap-timeout", options.tap_timeout)?;
+                // home-row-mod implies the release flavor and adds the
+                // keys of the same hand to :tap-keys
+                if !options.bilateral {
+                    match options.flavor {
+                        Flavor::Timeout => {}
+                        Flavor::Press => f.write_str(" :flavor press")?,
+                        Flavor::Release => f.write_str(" :flavor release")?,
+                    }
+                }
+                if !options.tap_keys.is_empty() && !options.bilateral {
+                    let keys: Vec<_> = options.tap_keys.iter().map(key_name).collect();
+                    write!(f, " :tap-keys ({})", keys.join(" "))?;
+                }
+                f.write_str(")")
+            }
+            Action::TapDance(dance) => {
+                let name = if dance.eager {
+                    "tap-dance-eager"
+                } else {
+                    "tap-dance"
+                };
+                write!(f, "({}", name)?;
+                let branches = [
+                    (":tap", &dance.tap),
+                    (":hold", &dance.hold),
+                    (":double-tap", &dance.double_tap),
+                    (":tap-hold", &dance.tap_hold),
+                ];
+                for (name, branch) in branches {
+                    if let Some(action) = branch {
+                        write!(f, " {} {}", name, action)?;
+                    }
+                }
+                option(f, ":term", dance.term)?;
+                f.write_str(")")
+            }
+            Action::OneShot(target, options) => {
+                write!(f, "(one-shot {}", target)?;
+                option(f, ":timeout", options.timeout)?;
+                if options.on_release {
+                    f.write_str(" :end release")?;
+                }
+                if options.pause_on_layer {
+                    f.write_str(" :pause-on-layer yes")?;
+                }
+                f.write_str(")")
+            }
+            Action::Multi(actions) => list(f, "multi", actions),
+            Action::Sequence(actions) => list(f, "macro", actions),
+            Action::LayerWhileHeld(layer) => write!(f, "(layer-while-held {})", layer),
+            Action::LayerSwitch(layer) => write!(f, "(layer-switch {})", layer),
+            Action::LayerToggle(layer) => write!(f, "(layer-toggle {})", layer),
+            Action::LayerTo(layer) => write!(f, "(layer-to {})", layer),
+            Action::LayerTapToggle(layer) => write!(f, "(layer-tap-toggle {})", layer),
+            Action::LayerWithMods(layer, mods) => {
+                write!(f, "(layer-with-mods {}", layer)?;
+                for key in mods.keys() {
+                    write!(f, " {}", key_name(&key))?;
+                }
+                f.write_str(")")
+            }
+            Action::CapsWord => f.write_str("caps-word"),
+            Action::Repeat => f.write_str("repeat"),
+            Action::AltRepeat => f.write_str("alt-repeat"),
+            Action::Leader => f.write_str("leader"),
+            Action::MouseMove(direction, options) | Action::MouseWheel(direction, options) => {
+                let name = match self {
+                    Action::MouseWheel(..) => "mouse-wheel",
+                    _ => "mouse-move",
+                };
+                write!(f, "({} {}", name, direction.name())?;
+                option(f, ":speed", options.speed)?;
+                option(f, ":interval", options.interval)?;
+                option(f, ":accel", options.accel)?;
+                f.write_str(")")
+            }
+            Action::Unicode('(') => f.write_str("lb"),
+            Action::Unicode(')') => f.write_str("rb"),
+            Action::Unicode(c) => write!(f, ".{}", c),
+            Action::Hold(key) | Action::Release(key) => f.write_str(&key_name(key)),
+        }
+    }
+}
+
+/// Short name of the key, the canonical one is rarely written in layouts
+fn key_name(key: &Key) -> String {
+    match key.info() {
+        Some(info) => info.names.get(1).unwrap_or(&info.names[0]).to_string(),
+        None => key.to_string(),
+    }
+}
+
+fn tap_hold_options(params: &[Expr]) -> Result<TapHoldOptions, Error> {
+    let mut options = TapHoldOptions::default();
+    keyword_options(params, TAP_HOLD_OPTIONS, |name, value| {
+        match name {
+            ":timeout" => options.timeout = Some(ms(value)?),
+            ":tap-timeout" => options.tap_timeout = Some(ms(value)?),
+            ":flavor" => {
+                options.flavor = match value.atom()? {
+                    "timeout" => Flavor::Timeout,
+                    "press" => Flavor::Press,
+                    "release" => Flavor::Release,
+                    x => {
+                        return Err(Error::new(value.span(), format!("Unknown flavor {:?}", x))
+                            .with_code(Code::UnknownOption)
+                            .with_suggestion(x, ["timeout", "press", "release"]));
+                    }
+                }
+            }
+            ":tap-keys" => {
+                options.tap_keys = value
+                    .list()?
+                    .iter()
+                    .map(|k| parser::parse_key(k.atom()?, k.span()))
+                    .collect::<Result<_, _>>()?
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    })?;
+    if !options.tap_keys.is_empty() && options.flavor == Flavor::Press {
+        return Err(Error::new(
+            params[0].span(),
+            ":tap-keys can't be used with the press flavor",
+        )
+        .with_note(":tap-keys implies the release flavor"));
+    }
+    Ok(options)
+}
+
+fn tap_dance(params: &[Expr]) -> Result<TapDance, Error> {
+    let mut dance = TapDance::default();
+    keyword_options(params, TAP_DANCE_OPTIONS, |name, value| {
+        let branch = match name {
+            ":tap" => &mut dance.tap,
+            ":hold" => &mut dance.hold,
+            ":double-tap" => &mut dance.double_tap,
+            ":tap-hold" => &mut dance.tap_hold,
+            ":term" => {
+                dance.term = Some(ms(value)?);
+                return Ok(());
+            }
+            _ => unreachable!(),
+        };
+        *branch = Some(Box::new(Action::from_expr(value)?));
+        Ok(())
+    })?;
+    Ok(dance)
+}
+
+fn one_shot_options(params: &[Expr]) -> Result<OneShotOptions, Error> {
+    let mut options = OneShotOptions::default();
+    keyword_options(params, ONE_SHOT_OPTIONS, |name, value| {
+        match name {
+            ":timeout" => options.timeout = Some(ms(value)?),
+            ":end" => {
+                options.on_release = match value.atom()? {
+                    "press" => false,
+                    "release" => true,
+                    x => {
+                        return Err(Error::new(value.span(), format!("Unknown end {:?}", x))
+                            .with_code(Code::UnknownOption)
+                            .with_suggestion(x, ["press", "release"]));
+                    }
+                }
+            }
+            ":pause-on-layer" => {
+                options.pause_on_layer = match value.atom()? {
+                    "yes" => true,
+                    "no" => false,
+                    x => {
+                        return Err(Error::new(
+                            value.span(),
+                            format!("Expected yes or no, found {:?}", x),
+                        )
+                        .with_code(Code::UnknownOption)
+                        .with_suggestion(x, ["yes", "no"]));
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    })?;
+    Ok(options)
+}
+
+fn mouse_options(params: &[Expr], wheel: bool) -> Result<MouseOptions, Error> {
+    let names = if wheel {
+        &MOUSE_OPTIONS[..2]
+    } else {
+        MOUSE_OPTIONS
+    };
+    let mut options = MouseOptions::default();
+    keyword_options(params, names, |name, value| {
+        let number = value
+            .atom()?
+            .parse::<u16>()
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| {
+                Error::new(
+                    value.span(),
+                    format!("Expected a positive number, found {}", value),
+                )
+            })?;
+        match name {
+            ":speed" => options.speed = Some(number),
+            ":interval" => options.interval = Some(number),
+            ":accel" => options.accel = Some(number),
+            _ => unreachable!(),
+        }
+        Ok(())
+    })?;
+    Ok(options)
+}
@@ -0,0 +1,71 @@
+use super::{
+    Action,
+    options::{ms, options},
+};
+use keys::keys::{Key, KeyIndex};
+use s_expression::{Code, Error, Expr, Expr::*, Span};
+use std::collections::HashMap;
+
+/// Keyword options of `defcombo`
+const OPTIONS: &[&str] = &[":timeout", ":layers"];
+
+/// Action of keys pressed together, `(defcombo (j k) esc)`
+#[derive(Debug, Clone)]
+pub struct Combo {
+    /// Keys from `defsrc`
+    pub keys: Vec<Key>,
+    pub action: Action,
+    /// Time to press all the keys, ms
+    pub timeout: Option<u16>,
+    /// Layers the combo works on, all layers if empty
+    pub layers: Vec<String>,
+}
+
+impl Combo {
+    /// Parses the parameters of a `defcombo` form, also returns the layer
+    /// names with their spans
+    pub fn from_def(
+        params: &[Expr],
+        source: &HashMap<Key, KeyIndex>,
+    ) -> Result<(Self, Vec<(String, Span)>), Error> {
+        let [List(keys, keys_span), action, params @ ..] = params else {
+            return Err("Expected (defcombo (keys ...) action)".into());
+        };
+        let keys = keys
+            .iter()
+            .map(|k| {
+                let key = parser::parse_key(k.atom()?, k.span())?;
+                if !source.contains_key(&key) {
+                    return Err(Error::new(k.span(), format!("Unknown key {:?}", key))
+                        .with_code(Code::UnknownKey)
+                        .with_note("the key is not part of defsrc"));
+                }
+                Ok(key)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if keys.len() < 2 {
+            return Err(Error::new(*keys_span, "Expected at least two keys"));
+        }
+        let mut combo = Combo {
+            keys,
+            action: Action::from_expr(action)?,
+            timeout: None,
+            layers: Vec::new(),
+        };
+        let mut layers = Vec::new();
+        options(params, OPTIONS, |name, value| {
+            match name {
+                ":timeout" => combo.timeout = Some(ms(value)?),
+                ":layers" => {
+                    for layer in value.list()? {
+                        layers.push((layer.atom()?.to_string(), layer.span()));
+                    }
+                    combo.layers = layers.iter().map(|(name, _)| name.clone()).collect();
+                }
+                _ => unreachable!(),
+            }
+            Ok(())
+        })?;
+        Ok((combo, layers))
+    }
+}
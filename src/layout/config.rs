@@ -0,0 +1,301 @@
+use super::options::ms;
+use keys::keys::Key;
+use s_expression::{Code, Error, Expr, Expr::*};
+
+const TAPPING_TERM: u16 = 200;
+const COMBO_TERM: u16 = 50;
+const ONE_SHOT_TIMEOUT: u16 = 1000;
+const CAPS_WORD_TIMEOUT: u16 = 5000;
+
+/// Keys caps word types shifted: letters, and `-` which becomes `_`. Kanata
+/// `caps-word` and QMK Caps Word both default to these
+pub const CAPS_WORD_SHIFTED: &[Key] = &[
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::Minus,
+];
+
+/// Keys that continue a caps word unshifted by default
+pub const CAPS_WORD_KEYS: &[Key] = &[
+    Key::One,
+    Key::Two,
+    Key::Three,
+    Key::Four,
+    Key::Five,
+    Key::Six,
+    Key::Seven,
+    Key::Eight,
+    Key::Nine,
+    Key::Zero,
+    Key::Backspace,
+    Key::Delete,
+];
+
+const OPTIONS: &[&str] = &[
+    "tapping-term",
+    "tap-timeout",
+    "hold-timeout",
+    "quick-tap",
+    "combo-term",
+    "one-shot-timeout",
+    "caps-word-timeout",
+    "caps-word-keys",
+];
+
+/// Timing settings in milliseconds, unset ones fall back to `tapping-term`
+/// (`combo-term` to 50, `one-shot-timeout` to 1000, `caps-word-timeout` to 5000)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Timing {
+    pub tapping_term: Option<u16>,
+    /// Window after a tap in which pressing the key again repeats the tap
+    pub quick_tap: Option<u16>,
+    pub tap_timeout: Option<u16>,
+    pub hold_timeout: Option<u16>,
+    pub combo_term: Option<u16>,
+    pub one_shot_timeout: Option<u16>,
+    pub caps_word_timeout: Option<u16>,
+}
+
+impl Timing {
+    pub fn tapping_term(&self) -> u16 {
+        self.tapping_term.unwrap_or(TAPPING_TERM)
+    }
+    pub fn quick_tap(&self) -> u16 {
+        self.quick_tap.unwrap_or(self.tapping_term())
+    }
+    /// Kanata `tap-repress-timeout`, defaults to `quick-tap`
+    pub fn tap_timeout(&self) -> u16 {
+        self.tap_timeout.unwrap_or(self.quick_tap())
+    }
+    pub fn hold_timeout(&self) -> u16 {
+        self.hold_timeout.unwrap_or(self.tapping_term())
+    }
+    pub fn combo_term(&self) -> u16 {
+        self.combo_term.unwrap_or(COMBO_TERM)
+    }
+    pub fn one_shot_timeout(&self) -> u16 {
+        self.one_shot_timeout.unwrap_or(ONE_SHOT_TIMEOUT)
+    }
+    pub fn caps_word_timeout(&self) -> u16 {
+        self.caps_word_timeout.unwrap_or(CAPS_WORD_TIMEOUT)
+    }
+
+    /// Settings of `self`, the unset ones taken from `other`
+    fn or(&self, other: &Timing) -> Timing {
+        Timing {
+            tapping_term: self.tapping_term.or(other.tapping_term),
+            quick_tap: self.quick_tap.or(other.quick_tap),
+            tap_timeout: self.tap_timeout.or(other.tap_timeout),
+            hold_timeout: self.hold_timeout.or(other.hold_timeout),
+            combo_term: self.combo_term.or(other.combo_term),
+            one_shot_timeout: self.one_shot_timeout.or(other.one_shot_timeout),
+            caps_word_timeout: self.caps_word_timeout.or(other.caps_word_timeout),
+        }
+    }
+
+    fn option(&mut self, name: &str) -> Option<&mut Option<u16>> {
+        Some(match name {
+            "tapping-term" => &mut self.tapping_term,
+            "quick-tap" => &mut self.quick_tap,
+            "tap-timeout" => &mut self.tap_timeout,
+            "hold-timeout" => &mut self.hold_timeout,
+            "combo-term" => &mut self.combo_term,
+            "one-shot-timeout" => &mut self.one_shot_timeout,
+            "caps-word-timeout" => &mut self.caps_word_timeout,
+            _ => return None,
+        })
+    }
+}
+
+/// Settings from `defcfg`. The `kanata` and `vial` sections override the
+/// global timing for their backend
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub timing: Timing,
+    kanata: Timing,
+    vial: Timing,
+    /// Options of the `kanata` section copied to the kanata `defcfg` as is
+    pub kanata_options: Vec<(String, String)>,
+    /// Keys that continue a caps word besides [`CAPS_WORD_SHIFTED`] and
+    /// [`CAPS_WORD_KEYS`]
+    pub caps_word_keys: Vec<Key>,
+}
+
+impl Config {
+    pub fn kanata(&self) -> Timing {
+        self.kanata.or(&self.timing)
+    }
+    pub fn vial(&self) -> Timing {
+        self.vial.or(&self.timing)
+    }
+
+    /// Keys that continue a caps word unshifted: the defaults, then the
+    /// `caps-word-keys` not among the defaults
+    pub fn caps_word_continue(&self) -> Vec<Key> {
+        let mut keys = CAPS_WORD_KEYS.to_vec();
+        for key in &self.caps_word_keys {
+            if !keys.contains(key) && !CAPS_WORD_SHIFTED.contains(key) {
+                keys.push(*key);
+            }
+        }
+        keys
+    }
+
+    /// Reads the parameters of a `(defcfg ...)` form
+    pub fn extend(&mut self, params: &[Expr]) -> Result<(), Error> {
+        let mut params = params.iter();
+        while let Some(param) = params.next() {
+            match param {
+                List(section, _) => self.section(section)?,
+                Atom(name, span) => {
+                    let value = params
+                        .next()
+                        .ok_or(Error::new(*span, format!("Expected a value of {:?}", name)))?;
+                    if *name == "caps-word-keys" {
+                        for key in value.list()? {
+                            let key = parser::parse_key(key.atom()?, key.span())?;
+                            self.caps_word_keys.push(key);
+                        }
+                        continue;
+                    }
+                    let option = self
+                        .timing
+                        .option(name)
+                        .ok_or_else(|| unknown_option(name, param, OPTIONS.iter().copied()))?;
+                    set(option, name, value)?;
+                }
+                x => return Err(Error::new(x.span(), format!("Unexpected {}", x))),
+            }
+        }
+        Ok(())
+    }
+
+    fn section(&mut self, section: &[Expr]) -> Result<(), Error> {
+        let [name, params @ ..] = section else {
+            return Ok(());
+        };
+        let target = name.atom()?;
+        let timing = match target {
+            "kanata" => &mut self.kanata,
+            "vial" => &mut self.vial,
+            _ => {
+                return Err(unknown_option(target, name, ["kanata", "vial"])
+                    .with_note("sections are named after backends"));
+            }
+        };
+        if params.len() % 2 != 0 {
+            return Err(Error::new(
+                name.span(),
+                format!("Expected option-value pairs in {:?}", target),
+            ));
+        }
+        for pair in params.chunks(2) {
+            let [option @ Atom(option_name, _), value] = pair else {
+                return Err(Error::new(pair[0].span(), "Expected option name"));
+            };
+            match timing.option(option_name) {
+                Some(timing) => set(timing, option_name, value)?,
+                None if target == "kanata" => {
+                    if self.kanata_options.iter().any(|(n, _)| n == option_name) {
+                        return Err(Error::new(
+                            option.span(),
+                            format!("Option {:?} is already set", option_name),
+                        ));
+                    }
+                    self.kanata_options
+                        .push((option_name.to_string(), value.to_string()));
+                }
+                None => return Err(unknown_option(option_name, option, OPTIONS.iter().copied())),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn set(option: &mut Option<u16>, name: &str, value: &Expr) -> Result<(), Error> {
+    if option.is_some() {
+        return Err(Error::new(
+            value.span(),
+            format!("Option {:?} is already set", name),
+        ));
+    }
+    *option = Some(ms(value)?);
+    Ok(())
+}
+
+fn unknown_option<'a>(
+    name: &str,
+    expr: &Expr,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Error {
+    Error::new(expr.span(), format!("Unknown option {:?}", name))
+        .with_code(Code::UnknownOption)
+        .with_suggestion(name, candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(content: &str) -> Result<Config, Error> {
+        let expr = s_expression::from_str(content).unwrap();
+        let mut config = Config::default();
+        config.extend(&expr.list()?[1..])?;
+        Ok(config)
+    }
+
+    #[test]
+    fn sections_override_global_timing() {
+        let config = config(
+            "(defcfg tapping-term 180 quick-tap 120
+                (kanata hold-timeout 250 process-unmapped-keys yes)
+                (vial tapping-term 220))",
+        )
+        .unwrap();
+        let kanata = config.kanata();
+        assert_eq!((kanata.tap_timeout(), kanata.hold_timeout()), (120, 250));
+        assert_eq!(config.vial().hold_timeout(), 220);
+        assert_eq!(config.kanata().combo_term(), COMBO_TERM);
+        assert_eq!(
+            config.kanata_options,
+            vec![("process-unmapped-keys".to_string(), "yes".to_string())]
+        );
+    }
+
+    #[test]
+    fn unknown_options() {
+        let e = config("(defcfg taping-term 180)").unwrap_err();
+        assert_eq!(e.code, Some(Code::UnknownOption));
+        assert_eq!(e.help.as_deref(), Some("did you mean `tapping-term`?"));
+
+        let e = config("(defcfg (vial process-unmapped-keys yes))").unwrap_err();
+        assert_eq!(e.code, Some(Code::UnknownOption));
+
+        let e = config("(defcfg tapping-term 180 (kanata tapping-term fast))").unwrap_err();
+        assert_eq!(e.message, "Expected milliseconds, found fast");
+    }
+}
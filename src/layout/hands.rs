@@ -0,0 +1,87 @@
+use super::{Action, Layout};
+use keys::keys::Key;
+use parser::Hand;
+use s_expression::{Error, Expr, Expr::*};
+use std::collections::HashMap;
+
+/// Reads `(defhands left (q w e) right (y u i))`
+pub(super) fn parse(params: &[Expr]) -> Result<HashMap<Key, Hand>, Error> {
+    let mut hands = HashMap::new();
+    for pair in params.chunks(2) {
+        let [Atom(name, span), List(keys, _)] = pair else {
+            return Err(Error::new(
+                pair[0].span(),
+                format!("Expected `hand (keys ...)`, found {}", pair[0]),
+            ));
+        };
+        let hand = match *name {
+            "left" => Hand::Left,
+            "right" => Hand::Right,
+            _ => {
+                return Err(Error::new(*span, format!("Unknown hand {:?}", name))
+                    .with_suggestion(name, ["left", "right"]));
+            }
+        };
+        for key in keys {
+            hands.insert(parser::parse_key(key.atom()?, key.span())?, hand);
+        }
+    }
+    Ok(hands)
+}
+
+impl Layout {
+    /// Fills the tap keys of every `home-row-mod` with the keys of its hand,
+    /// so it holds only when the next key is on the other hand. Hands come
+    /// from `defmatrix` geometry, `declared` ones take precedence
+    pub(super) fn resolve_hands(&mut self, declared: &HashMap<Key, Hand>) -> Result<(), Error> {
+        let mut hands = self.keyboard.matrix.hands();
+        hands.extend(declared);
+        let mut source: Vec<_> = self.keyboard.source.iter().collect();
+        source.sort_by_key(|(_, i)| **i);
+        let keys: HashMap<_, _> = source.iter().map(|(k, i)| (**i, **k)).collect();
+
+        for layer in self.layers.values_mut() {
+            for (index, action) in layer.keys.iter_mut() {
+                let Some(key) = keys.get(index) else {
+                    continue;
+                };
+                let hand = hands.get(key).copied();
+                let same_hand: Vec<Key> = source
+                    .iter()
+                    .map(|(k, _)| **k)
+                    .filter(|k| k != key && hand.is_some_and(|h| hands.get(k) == Some(&h)))
+                    .collect();
+                *action = bilateral(action, key, hand, &same_hand)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn bilateral(
+    action: &Action,
+    key: &Key,
+    hand: Option<Hand>,
+    same_hand: &[Key],
+) -> Result<Action, Error> {
+    match action {
+        Action::TapHold(tap, hold, options) if options.bilateral => {
+            if hand.is_none() {
+                return Err(
+                    Error::from(format!("Key {:?} with home-row-mod has no hand", key))
+                        .with_note("the key is not in defmatrix")
+                        .with_help("declare its hand with defhands"),
+                );
+            }
+            let mut options = options.clone();
+            for k in same_hand {
+                if !options.tap_keys.contains(k) {
+                    options.tap_keys.push(*k);
+                }
+            }
+            options.bilateral = false;
+            Ok(Action::TapHold(tap.clone(), hold.clone(), options))
+        }
+        other => other.try_map(&mut |a| bilateral(a, key, hand, same_hand)),
+    }
+}
@@ -0,0 +1,283 @@
+use super::Layout;
+use s_expression::{Code, Error, Expr, Span};
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Component, Path, PathBuf},
+};
+
+/// All files of a layout: the root file and everything it `include`s.
+/// Contents are kept in one text, so spans of the parsed expressions tell
+/// which file they come from
+#[derive(Debug, Default)]
+pub struct Sources {
+    text: String,
+    files: Vec<File>,
+    /// File loaded by the `include` form starting at the offset
+    includes: HashMap<usize, usize>,
+}
+
+#[derive(Debug)]
+struct File {
+    path: PathBuf,
+    start: usize,
+    end: usize,
+}
+
+impl Sources {
+    pub fn load(&mut self, path: &Path) -> Result<(), Vec<Error>> {
+        self.load_with(path, &|path| std::fs::read_to_string(path))
+    }
+
+    /// Loads `path` and its includes with `read`
+    pub fn load_with<F>(&mut self, path: &Path, read: &F) -> Result<(), Vec<Error>>
+    where
+        F: Fn(&Path) -> std::io::Result<String>,
+    {
+        self.file(normalize(path), None, &mut Vec::new(), read)
+            .map(|_| ())
+    }
+
+    fn file<F>(
+        &mut self,
+        path: PathBuf,
+        from: Option<Span>,
+        stack: &mut Vec<usize>,
+        read: &F,
+    ) -> Result<usize, Vec<Error>>
+    where
+        F: Fn(&Path) -> std::io::Result<String>,
+    {
+        let at = |message: String| {
+            let error = match from {
+                Some(span) => Error::new(span, message),
+                None => Error::from(message),
+            };
+            vec![error.with_code(Code::Include)]
+        };
+        if let Some(index) = self.files.iter().position(|f| f.path == path) {
+            if stack.contains(&index) {
+                let chain: Vec<_> = stack[stack.iter().position(|i| *i == index).unwrap_or(0)..]
+                    .iter()
+                    .chain([&index])
+                    .map(|i| self.files[*i].path.display().to_string())
+                    .collect();
+                return Err(at(format!("Include cycle: {}", chain.join(" -> "))));
+            }
+            return Ok(index);
+        }
+
+        let content = read(&path).map_err(|e| at(format!("{}: {}", path.display(), e)))?;
+        let index = self.files.len();
+        let start = self.text.len();
+        self.text.push_str(&content);
+        self.files.push(File {
+            path,
+            start,
+            end: self.text.len(),
+        });
+        // Keeps a line comment at the end of the file from swallowing the next one
+        self.text.push('\n');
+
+        let includes = self.file_includes(index)?;
+        stack.push(index);
+        for (span, target) in includes {
+            let dir = self.files[index].path.parent().unwrap_or(Path::new(""));
+            let included = self.file(normalize(&dir.join(target)), Some(span), stack, read)?;
+            self.includes.insert(span.start, included);
+        }
+        stack.pop();
+        Ok(index)
+    }
+
+    /// `include` forms of a file and their paths
+    fn file_includes(&self, index: usize) -> Result<Vec<(Span, String)>, Vec<Error>> {
+        let file = &self.files[index];
+        let shift = |span: Span| Span::new(span.start + file.start, span.end + file.start);
+        let root =
+            s_expression::from_str_list(&self.text[file.start..file.end]).map_err(|errors| {
+                errors
+                    .into_iter()
+                    .map(|e| {
+                        let mut e = Error::from(e);
+                        e.span = e.span.map(shift);
+                        e
+                    })
+                    .collect::<Vec<_>>()
+            })?;
+        let mut includes = Vec::new();
+        for expr in root.list().map_err(|e| vec![e])? {
+            let Expr::List(list, span) = expr else {
+                continue;
+            };
+            match list.as_slice() {
+                [Expr::Atom("include", _), Expr::Str(path, _)] => {
+                    includes.push((shift(*span), path.clone()))
+                }
+                [Expr::Atom("include", _), ..] => {
+                    return Err(vec![
+                        Error::new(shift(*span), "Expected (include \"path\")")
+                            .with_code(Code::Include),
+                    ]);
+                }
+                _ => {}
+            }
+        }
+        Ok(includes)
+    }
+
+    /// Parses the loaded files into a layout, every `include` is replaced by
+    /// the forms of the included file the first time the file is included
+    pub fn layout(&self) -> Result<Layout, Vec<Error>> {
+        let root = s_expression::from_str_list(&self.text)
+            .map_err(|errors| errors.into_iter().map(Error::from).collect::<Vec<_>>())?;
+        let forms = root.list().map_err(|e| vec![e])?;
+        let mut seen = HashSet::from([0]);
+        let expr = Expr::List(self.forms(0, forms, &mut seen), root.span());
+        Layout::from_expr(&expr).map_err(|e| vec![e])
+    }
+
+    fn forms<'a>(
+        &self,
+        index: usize,
+        all: &[Expr<'a>],
+        seen: &mut HashSet<usize>,
+    ) -> Vec<Expr<'a>> {
+        let file = &self.files[index];
+        let mut forms = Vec::new();
+        for expr in all {
+            let span = expr.span();
+            if !(file.start..file.end).contains(&span.start) {
+                continue;
+            }
+            match self.includes.get(&span.start) {
+                Some(&included) => {
+                    if seen.insert(included) {
+                        forms.extend(self.forms(included, all, seen));
+                    }
+                }
+                None => forms.push(expr.clone()),
+            }
+        }
+        forms
+    }
+
+    /// Renders errors against the files they point into
+    pub fn render(&self, errors: &[Error]) -> String {
+        errors
+            .iter()
+            .map(|e| {
+                let file = e
+                    .span
+                    .and_then(|span| {
+                        self.files
+                            .iter()
+                            .find(|f| (f.start..=f.end).contains(&span.start))
+                    })
+                    .or(self.files.first());
+                let Some(file) = file else {
+                    return format!("error: {}", e.message);
+                };
+                let mut e = e.clone();
+                e.span = e
+                    .span
+                    .map(|span| Span::new(span.start - file.start, span.end - file.start));
+                e.render(
+                    &file.path.display().to_string(),
+                    &self.text[file.start..file.end],
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+/// Resolves `.` and `..` without touching the file system
+fn normalize(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(result.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                result.pop();
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(files: &[(&str, &str)]) -> (Sources, Result<Layout, Vec<Error>>) {
+        let files: HashMap<PathBuf, String> = files
+            .iter()
+            .map(|(path, content)| (PathBuf::from(path), content.to_string()))
+            .collect();
+        let read = |path: &Path| {
+            files
+                .get(path)
+                .cloned()
+                .ok_or(std::io::Error::from(std::io::ErrorKind::NotFound))
+        };
+        let mut sources = Sources::default();
+        let result = sources
+            .load_with(Path::new("dir/main.rkl"), &read)
+            .and_then(|_| sources.layout());
+        (sources, result)
+    }
+
+    #[test]
+    fn includes_relative_and_once() {
+        let (_, layout) = load(&[
+            (
+                "dir/main.rkl",
+                "(include \"common/base.rkl\") ;; base\n(include \"./common/base.rkl\")\n(deflayer main (t q) (t w))",
+            ),
+            (
+                "dir/common/base.rkl",
+                "(include \"../src.rkl\")\n(deftemplate t ($x) (multi S $x))",
+            ),
+            ("dir/src.rkl", "(defsrc q w)"),
+        ]);
+        let layout = layout.unwrap();
+        assert_eq!(layout.layers.len(), 1);
+    }
+
+    #[test]
+    fn include_cycle() {
+        let (sources, layout) = load(&[
+            ("dir/main.rkl", "(include \"a.rkl\")"),
+            ("dir/a.rkl", "(defsrc q)\n(include \"main.rkl\")"),
+        ]);
+        let errors = layout.unwrap_err();
+        assert_eq!(
+            sources.render(&errors),
+            "error[E0007]: Include cycle: dir/main.rkl -> dir/a.rkl -> dir/main.rkl\n --> dir/a.rkl:2:1\n  |\n2 | (include \"main.rkl\")\n  | ^^^^^^^^^^^^^^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn errors_point_into_included_file() {
+        let (sources, layout) = load(&[
+            ("dir/main.rkl", "(defsrc q)\n(include \"a.rkl\")"),
+            ("dir/a.rkl", ";; layer\n(deflayer x qq)"),
+        ]);
+        let errors = layout.unwrap_err();
+        assert!(
+            sources.render(&errors).contains(" --> dir/a.rkl:2:13\n"),
+            "{}",
+            sources.render(&errors)
+        );
+
+        let (sources, layout) = load(&[("dir/main.rkl", "(include \"missing.rkl\")")]);
+        assert!(
+            sources.render(&layout.unwrap_err()).starts_with(
+                "error[E0007]: dir/missing.rkl: entity not found\n --> dir/main.rkl:1:1"
+            )
+        );
+    }
+}
@@ -1,25 +1,28 @@
 use crate::layout::action::Action;
-use keys::keys::{Key, KeyIndex};
-use s_expression::Expr::{self, *};
+use keys::{
+    keys::{Key, KeyIndex},
+    mods::Mods,
+};
+use s_expression::{
+    Code, Error,
+    Expr::{self, *},
+    Span,
+};
 use std::{collections::HashMap, str::FromStr};
 
 #[derive(Debug, Clone)]
 pub struct Override {
     pub key: Key,
-    pub mods: Vec<Key>,
+    pub mods: Mods,
     pub action: Action,
 }
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub enum Keymap {
+    #[default]
     En,
     Ru,
 }
 
-impl Default for Keymap {
-    fn default() -> Self {
-        Keymap::En
-    }
-}
 impl FromStr for Keymap {
     type Err = ();
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -39,33 +42,42 @@ pub struct Layer {
     pub overrides: Vec<Override>,
     pub index: usize,
     pub keymap: Keymap,
+    /// Name in the first definition of the layer, `None` for `src`
+    pub span: Option<Span>,
 }
 
 impl Layer {
     pub fn child(&self, name: String, index: usize) -> Self {
         Self {
-            name: name,
+            name,
             parent: self.name.clone(),
             keys: self.keys.clone(),
             overrides: self.overrides.clone(),
-            index: index,
+            index,
             keymap: self.keymap.clone(),
+            span: None,
+        }
+    }
+    /// Points `e` at the layer unless it already has a location
+    pub fn locate(&self, e: Error) -> Error {
+        match self.span {
+            Some(span) => e.or_at(span),
+            None => e,
         }
     }
     pub fn from_keyboard(source: &HashMap<Key, KeyIndex>) -> Self {
         Self {
             name: "src".to_string(),
             parent: String::new(),
-            keys: source
-                .iter()
-                .map(|(k, v)| (*v, Action::Tap(k.clone())))
-                .collect(),
+            keys: source.iter().map(|(k, v)| (*v, Action::Tap(*k))).collect(),
             overrides: Default::default(),
             index: 0,
             keymap: Default::default(),
+            span: None,
         }
     }
-    pub fn from_def(params: &[Expr<'_>], index: usize) -> Result<Self, String> {
+    pub fn from_def(params: &[Expr<'_>], index: usize) -> Result<Self, Error> {
+        let span = Self::name_span(params);
         let (name, parent, actions) = Self::get_name(params)?;
         Ok(Self {
             name: name.to_string(),
@@ -74,84 +86,110 @@ impl Layer {
                 HashMap::with_capacity(actions.len()),
                 |mut acc, (i, e)| {
                     acc.insert(
-                        i.try_into().map_err(|_| "Parse error".to_string())?,
+                        i.try_into()
+                            .map_err(|_| Error::new(e.span(), "Too many keys"))?,
                         Action::from_expr(e)?,
                     );
-                    Ok::<HashMap<KeyIndex, Action>, String>(acc)
+                    Ok::<HashMap<KeyIndex, Action>, Error>(acc)
                 },
             )?,
             overrides: Default::default(),
             keymap: Default::default(),
-            index: index,
+            index,
+            span,
         })
     }
 
+    /// Layers held or toggled on top of this one, they go after it in the
+    /// layer order. `layer-switch` and `layer-to` replace the active layers, so
+    /// their targets don't depend on this one (and often switch back to it)
     pub fn get_dependencies(&self) -> Vec<&str> {
         let mut layers: Vec<_> = self
             .keys
             .values()
-            .flat_map(|k| k.layer_while_held_names())
+            .flat_map(|k| {
+                let mut names = k.layer_while_held_names();
+                names.extend(k.layer_toggle_names());
+                names
+            })
             .filter(|x| *x != self.name)
             .collect();
         layers.dedup();
         layers
     }
 
+    /// Place of the layer name in the parameters of a layer definition
+    pub fn name_span(params: &[Expr]) -> Option<Span> {
+        Some(match params.first()? {
+            List(list, _) => list.first()?.span(),
+            e => e.span(),
+        })
+    }
+
     pub fn get_name<'a>(
         params: &'a [Expr<'a>],
-    ) -> Result<(&'a str, &'a str, &'a [Expr<'a>]), String> {
+    ) -> Result<(&'a str, &'a str, &'a [Expr<'a>]), Error> {
         let [name, params @ ..] = params else {
-            return Err("Syntax error".to_string());
+            return Err("Expected layer name".into());
         };
         let (name, parent) = match name {
-            Atom(x) => match *x {
+            Atom(x, span) => match *x {
                 "default" => ("default", "src"),
-                "src" => return Err("Cannot override src layer".to_string()),
+                "src" => return Err(Error::new(*span, "Cannot override src layer")),
                 name => (name, "default"),
             },
-            List(xs) => {
-                if let [Atom(name), Atom(parent)] = xs.as_slice() {
+            List(xs, span) => {
+                if let [Atom(name, _), Atom(parent, _)] = xs.as_slice() {
                     (*name, *parent)
                 } else {
-                    return Err("Syntax error".to_string());
+                    return Err(Error::new(*span, "Syntax error"));
                 }
             }
+            Str(_, span) => return Err(Error::new(*span, "Expected layer name")),
         };
         Ok((
             name,
             parent,
             match params {
-                [List(x)] => x,
+                [List(x, _)] => x,
                 _ => params,
             },
         ))
     }
+    /// Layer with the mapped keys and the place of every entry in order
     pub fn from_map(
         params: &[Expr<'_>],
         index_by_key: &HashMap<Key, KeyIndex>,
-    ) -> Result<Self, String> {
+    ) -> Result<(Self, Vec<(KeyIndex, Span)>), Error> {
+        let span = Self::name_span(params);
         let (name, parent, params) = Self::get_name(params)?;
-        Ok(Layer {
+        let mut entries = Vec::with_capacity(params.len() / 2);
+        let layer = Layer {
             name: name.to_string(),
             parent: parent.to_string(),
-            keys: params.chunks(2).into_iter().try_fold(
+            keys: params.chunks(2).try_fold(
                 HashMap::with_capacity(params.len()),
                 |mut acc, v| {
-                    let [Atom(key), expr] = v else {
-                        return Err("Syntax error".to_string());
+                    let [Atom(key, span), expr] = v else {
+                        return Err(Error::new(v[0].span(), "Syntax error"));
                     };
-                    let src: Key = key.parse().map_err(|_| format!("Unknown key {:?}", key))?;
-                    let index = index_by_key
-                        .get(&src)
-                        .ok_or(format!("Index for {:?} not found", src))?;
+                    let src = parser::parse_key(key, *span)?;
+                    let index = index_by_key.get(&src).ok_or_else(|| {
+                        Error::new(*span, format!("Index for {:?} not found", src))
+                            .with_code(Code::UnknownKey)
+                            .with_note("the key is not part of defsrc")
+                    })?;
                     let action = Action::from_expr(expr)?;
                     acc.insert(*index, action);
+                    entries.push((*index, *span));
                     Ok(acc)
                 },
             )?,
             overrides: Default::default(),
             keymap: Default::default(),
             index: 0,
-        })
+            span,
+        };
+        Ok((layer, entries))
     }
 }
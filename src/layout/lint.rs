@@ -0,0 +1,243 @@
+use super::{Action, Layer, Layout};
+use s_expression::{Code, Diagnostic, Expr, Expr::*, Span};
+use std::collections::{HashMap, HashSet};
+
+/// Warnings about definitions that never take effect: templates that are
+/// never expanded, aliases that are never referenced and layers that no
+/// action activates. `expr` is the layout before preprocessing, `root` after,
+/// `layout` is built from `root` with `aliases` not resolved yet
+pub fn lint(
+    expr: &Expr,
+    root: &Expr,
+    layout: &Layout,
+    aliases: &HashMap<String, Action>,
+) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+
+    // Templates used outside of `deftemplate`, then the ones their bodies use
+    let templates: HashMap<&str, (Span, &Expr)> = forms(expr, &["deftemplate"])
+        .flat_map(|params| params.chunks(3))
+        .filter_map(|chunk| match chunk {
+            [Atom(name, span), _, body] => Some((*name, (*span, body))),
+            _ => None,
+        })
+        .collect();
+    let mut pending = Vec::new();
+    for form in expr.list().map(Vec::as_slice).unwrap_or_default() {
+        if !matches!(
+            form.list().map(Vec::as_slice),
+            Ok([Atom("deftemplate", _), ..])
+        ) {
+            heads(form, &mut pending);
+        }
+    }
+    let mut used = HashSet::new();
+    while let Some(name) = pending.pop() {
+        if let Some((_, body)) = templates.get(name)
+            && used.insert(name)
+        {
+            heads(body, &mut pending);
+        }
+    }
+    warnings.extend(
+        definitions(expr, "deftemplate", 3)
+            .iter()
+            .filter(|(name, _)| !used.contains(name))
+            .map(|(name, span)| {
+                Diagnostic::warning(*span, format!("Template {:?} is never used", name))
+                    .with_code(Code::UnusedTemplate)
+            }),
+    );
+
+    // Aliases used by the layout, then the ones their definitions use
+    let layers = layout.layers.values().filter(|l| l.name != "src");
+    let actions = layers
+        .clone()
+        .flat_map(|l| l.keys.values().chain(l.overrides.iter().map(|o| &o.action)))
+        .chain(layout.keymaps.values())
+        .chain(layout.combos.iter().map(|c| &c.action))
+        .chain(layout.sequences.iter().map(|s| &s.action));
+    let mut pending: Vec<&str> = actions.flat_map(|a| a.alias_names()).collect();
+    let mut used = HashSet::new();
+    while let Some(name) = pending.pop() {
+        if let Some(action) = aliases.get(name)
+            && used.insert(name)
+        {
+            pending.extend(action.alias_names());
+        }
+    }
+    warnings.extend(
+        definitions(root, "defalias", 2)
+            .iter()
+            .filter(|(name, _)| !used.contains(name))
+            .map(|(name, span)| {
+                Diagnostic::warning(*span, format!("Alias {:?} is never used", name))
+                    .with_code(Code::UnusedAlias)
+            }),
+    );
+
+    // Layers activated by an action of another layer, a combo or a sequence
+    let activated = |action: &Action, layer: Option<&str>| -> Vec<String> {
+        let Ok(action) = action.resolve_aliases(aliases) else {
+            return vec![];
+        };
+        let mut names = action.layer_while_held_names();
+        names.extend(action.layer_toggle_names());
+        names.extend(action.layer_switch_names());
+        names
+            .into_iter()
+            .filter(|name| Some(*name) != layer)
+            .map(str::to_string)
+            .collect()
+    };
+    let mut used: HashSet<String> = HashSet::from(["default".to_string()]);
+    for layer in layers {
+        let actions = layer.keys.values();
+        for action in actions.chain(layer.overrides.iter().map(|o| &o.action)) {
+            used.extend(activated(action, Some(&layer.name)));
+        }
+    }
+    let actions = layout.keymaps.values();
+    let actions = actions
+        .chain(layout.combos.iter().map(|c| &c.action))
+        .chain(layout.sequences.iter().map(|s| &s.action));
+    for action in actions {
+        used.extend(activated(action, None));
+    }
+    let mut reported = HashSet::new();
+    for (name, span) in self::layers(root) {
+        if !used.contains(name) && reported.insert(name) {
+            warnings.push(
+                Diagnostic::warning(span, format!("Layer {:?} is never activated", name))
+                    .with_code(Code::UnusedLayer)
+                    .with_help(format!(
+                        "activate it with (layer-while-held {}) or (layer-switch {})",
+                        name, name
+                    )),
+            );
+        }
+    }
+    warnings
+}
+
+/// Names at the head of every list in `expr`
+fn heads<'a>(expr: &Expr<'a>, names: &mut Vec<&'a str>) {
+    if let List(list, _) = expr {
+        if let Some(Atom(name, _)) = list.first() {
+            names.push(name);
+        }
+        list.iter().for_each(|e| heads(e, names));
+    }
+}
+
+/// Names defined by top-level `form`s, which are split into chunks of `size`
+/// starting with the name
+fn definitions<'a>(root: &Expr<'a>, form: &str, size: usize) -> Vec<(&'a str, Span)> {
+    forms(root, &[form])
+        .flat_map(|params| params.chunks(size))
+        .filter_map(|chunk| match chunk.first() {
+            Some(Atom(name, span)) => Some((*name, *span)),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Layers defined by `deflayer`, `deflayermap` and `defoverride`
+fn layers<'a>(root: &'a Expr<'a>) -> Vec<(&'a str, Span)> {
+    forms(root, &["deflayer", "deflayermap", "defoverride"])
+        .filter_map(|params| {
+            let (name, _, _) = Layer::get_name(params).ok()?;
+            Some((name, Layer::name_span(params)?))
+        })
+        .collect()
+}
+
+fn forms<'a, 'b>(root: &'b Expr<'a>, names: &'b [&str]) -> impl Iterator<Item = &'b [Expr<'a>]> {
+    root.list()
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(move |form| match form.list().ok()?.as_slice() {
+            [Atom(name, _), params @ ..] if names.contains(name) => Some(params),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warnings(content: &str) -> Vec<(Code, String)> {
+        let layout: Layout = content.parse().unwrap();
+        let mut warnings: Vec<_> = layout
+            .warnings
+            .into_iter()
+            .map(|w| (w.code.unwrap(), w.message))
+            .collect();
+        warnings.sort_by_key(|(code, _)| code.as_str());
+        warnings
+    }
+
+    #[test]
+    fn unused_definitions() {
+        let content = r#"
+            (defsrc q w e)
+            (deftemplate used ($x) (multi C $x) unused ($x) $x)
+            (defalias nav (layer-while-held nav) copy (used c) paste (used v))
+            (deflayer default @nav @copy e)
+            (deflayer nav _ _ _)
+            (deflayer (sym nav) _ _ _)
+            (deflayer fn _ _ _)
+            (deflayermap fn q a w b q c)
+        "#;
+        assert_eq!(
+            warnings(content),
+            vec![
+                (Code::UnusedAlias, "Alias \"paste\" is never used".into()),
+                (
+                    Code::UnusedTemplate,
+                    "Template \"unused\" is never used".into()
+                ),
+                (Code::UnusedLayer, "Layer \"sym\" is never activated".into()),
+                (Code::UnusedLayer, "Layer \"fn\" is never activated".into()),
+                (
+                    Code::OverwrittenEntry,
+                    "Entry of layer \"fn\" is overwritten".into()
+                ),
+            ]
+        );
+        assert!(warnings("(defsrc q)\n(deflayer default q)").is_empty());
+    }
+
+    #[test]
+    fn used_only_by_unused_definitions() {
+        let content = r#"
+            (defsrc q w e)
+            (deftemplate inner ($x) (multi C $x) outer ($x) (inner $x))
+            (defalias a (macro q @b) b (multi C q) c (multi S q))
+            (deflayer default @c w e)
+            (deflayer base _ _ _)
+            (deflayer (sym base) _ _ _)
+        "#;
+        assert_eq!(
+            warnings(content),
+            vec![
+                (Code::UnusedAlias, "Alias \"a\" is never used".into()),
+                (Code::UnusedAlias, "Alias \"b\" is never used".into()),
+                (
+                    Code::UnusedTemplate,
+                    "Template \"inner\" is never used".into()
+                ),
+                (
+                    Code::UnusedTemplate,
+                    "Template \"outer\" is never used".into()
+                ),
+                (
+                    Code::UnusedLayer,
+                    "Layer \"base\" is never activated".into()
+                ),
+                (Code::UnusedLayer, "Layer \"sym\" is never activated".into()),
+            ]
+        );
+    }
+}
@@ -1,19 +1,33 @@
+use keys::{keys::KeyIndex, mods::Mods};
 use parser::{Keyboard, parse_vial};
-use s_expression::Expr::*;
+use s_expression::{Code, Diagnostic, Error, Expr, Expr::*, Span};
 use std::{
     collections::{HashMap, HashSet},
     str::FromStr,
 };
 
 mod action;
+mod combo;
+mod config;
+mod hands;
+mod include;
 mod layer;
+mod lint;
+mod options;
 mod preprocess;
+mod reach;
+mod sequence;
 mod template;
 mod unicode;
 mod unwrap;
-pub use action::Action;
+use action::LAYER_ACTIONS;
+pub use action::{Action, Direction, Flavor, MouseOptions, TapHoldOptions};
+pub use combo::Combo;
+pub use config::{CAPS_WORD_SHIFTED, Config, Timing};
+pub use include::Sources;
 pub use layer::{Keymap, Layer, Override};
 use preprocess::preprocess;
+pub use sequence::Sequence;
 use unicode::unicode;
 
 #[derive(Debug, Default)]
@@ -21,12 +35,17 @@ pub struct Layout {
     pub layers: HashMap<String, Layer>,
     pub keyboard: Keyboard,
     pub keymaps: HashMap<Keymap, Action>,
+    pub config: Config,
+    pub combos: Vec<Combo>,
+    pub sequences: Vec<Sequence>,
+    /// Lint findings, see [`lint::lint`]
+    pub warnings: Vec<Diagnostic>,
 }
 impl Layout {
     fn new() -> Self {
         Self::default()
     }
-    fn prepare_layers(&mut self, aliases: &HashMap<String, Action>) -> Result<(), String> {
+    fn prepare_layers(&mut self, aliases: &HashMap<String, Action>) -> Result<(), Error> {
         let layer_names: Vec<String> = self.layers.keys().cloned().collect();
 
         for name in &layer_names {
@@ -36,7 +55,24 @@ impl Layout {
                 .ok_or(format!("Layer {:?} not found", name))?;
             for action in layer.keys.values_mut() {
                 *action = action.resolve_aliases(aliases)?;
+                action.check_one_shots()?;
             }
+            for o in layer.overrides.iter_mut() {
+                o.action = o.action.resolve_aliases(aliases)?;
+                o.action.check_one_shots()?;
+            }
+        }
+        for action in self.keymaps.values_mut() {
+            *action = action.resolve_aliases(aliases)?;
+            action.check_one_shots()?;
+        }
+        for combo in self.combos.iter_mut() {
+            combo.action = combo.action.resolve_aliases(aliases)?;
+            combo.action.check_one_shots()?;
+        }
+        for sequence in self.sequences.iter_mut() {
+            sequence.action = sequence.action.resolve_aliases(aliases)?;
+            sequence.action.check_one_shots()?;
         }
 
         for name in &layer_names {
@@ -77,8 +113,8 @@ impl Layout {
 
         let new_layers: Vec<Layer> = self
             .layers
-            .iter()
-            .flat_map(|(_, l)| {
+            .values()
+            .flat_map(|l| {
                 let deps = l.get_dependencies();
                 let mut copies = HashSet::<String>::new();
                 let mut new = deps
@@ -108,7 +144,7 @@ impl Layout {
                         Some(new)
                     })
                     .collect::<Vec<_>>();
-                if new.len() > 0 {
+                if !new.is_empty() {
                     let mut s = l.clone();
                     s.keys.values_mut().for_each(|v| {
                         v.map_layer_while_held(&|x| {
@@ -141,16 +177,31 @@ impl Layout {
             Ok::<_, String>(())
         })?;
 
+        // Combos and sequences type in the keymap of the default layer
+        let keymap = self
+            .layers
+            .get("default")
+            .map(|l| l.keymap.clone())
+            .unwrap_or_default();
+        let actions = self
+            .combos
+            .iter_mut()
+            .map(|c| &mut c.action)
+            .chain(self.sequences.iter_mut().map(|s| &mut s.action));
+        for action in actions {
+            *action = resolve_unicode(action, &keymap, &self.keymaps)?;
+        }
+
         Ok(())
     }
-    fn layer_from(&self, parent: String, name: String, i: usize) -> Result<Layer, String> {
+    fn layer_from(&self, parent: String, name: String, i: usize) -> Result<Layer, Error> {
         let Some(parent) = self
             .layers
             .get(&name)
             .or_else(|| self.layers.get(&parent))
-            .or_else(|| self.layers.get(&"src".to_string()))
+            .or_else(|| self.layers.get("src"))
         else {
-            return Err(format!("Layer {:?} not defined", parent));
+            return Err(self.unknown_layer(&parent, None));
         };
         if parent.name == name {
             Ok(parent.clone())
@@ -158,23 +209,70 @@ impl Layout {
             Ok(parent.child(name, i))
         }
     }
+    fn unknown_layer(&self, name: &str, span: Option<Span>) -> Error {
+        Error {
+            span,
+            ..Error::from(format!("Layer {:?} not defined", name))
+        }
+        .with_code(Code::UnknownLayer)
+        .with_suggestion(name, self.layers.keys().map(String::as_str))
+    }
+    /// Points references to undefined layers and aliases at their place in
+    /// the source
+    fn check_references(
+        &self,
+        expr: &Expr,
+        aliases: &HashMap<String, Action>,
+    ) -> Result<(), Error> {
+        match expr {
+            Atom(atom, span) => match atom.strip_prefix("@") {
+                Some(alias) if !alias.is_empty() && !aliases.contains_key(alias) => {
+                    Err(Error::new(*span, format!("Alias @{} not defined", alias))
+                        .with_code(Code::UnknownAlias)
+                        .with_suggestion(alias, aliases.keys().map(String::as_str)))
+                }
+                _ => Ok(()),
+            },
+            List(list, _) => {
+                if let [Atom(action, _), Atom(layer, span), ..] = list.as_slice()
+                    && LAYER_ACTIONS.contains(action)
+                    && !self.layers.contains_key(*layer)
+                {
+                    return Err(self.unknown_layer(layer, Some(*span)));
+                }
+                list.iter()
+                    .try_for_each(|e| self.check_references(e, aliases))
+            }
+            Str(..) => Ok(()),
+        }
+    }
 }
 
 impl FromStr for Layout {
-    type Err = String;
+    type Err = Vec<Error>;
     fn from_str(content: &str) -> Result<Self, Self::Err> {
-        let content = format!("({})", content);
-        let expr = s_expression::from_str(content.as_str()).map_err(|_| "Parse error")?;
+        let expr = s_expression::from_str_list(content)
+            .map_err(|errors| errors.into_iter().map(Error::from).collect::<Vec<_>>())?;
+        Self::from_expr(&expr).map_err(|e| vec![e])
+    }
+}
+
+impl Layout {
+    fn from_expr(expr: &Expr) -> Result<Self, Error> {
         let mut layout = Self::new();
 
-        let root = preprocess(&expr)?;
+        let root = preprocess(expr)?;
         let mut aliases: HashMap<String, Action> = HashMap::new();
+        let mut alias_spans: Vec<(String, Span)> = Vec::new();
+        let mut entries: HashMap<(String, KeyIndex), Span> = HashMap::new();
+        let mut hands = HashMap::new();
+        let mut combo_layers: Vec<(String, Span)> = Vec::new();
         root.list()?
             .iter()
             .enumerate()
-            .try_for_each(|(i, r)| -> Result<(), String> {
+            .try_for_each(|(i, r)| -> Result<(), Error> {
                 let [name, params @ ..] = r.list()?.as_slice() else {
-                    return Err("Exprected name".to_string());
+                    return Err(Error::new(r.span(), "Exprected name"));
                 };
                 match name.atom()? {
                     "defsrc" => {
@@ -184,7 +282,10 @@ impl FromStr for Layout {
                         let src = layout.keyboard.source.len();
                         let dst = keymap.len();
                         if src != 0 && src != dst {
-                            return Err(format!("Expected {} keys, found {}", src, dst));
+                            return Err(Error::new(
+                                r.span(),
+                                format!("Expected {} keys, found {}", src, dst),
+                            ));
                         }
                         layout.keyboard.source = keymap;
 
@@ -192,30 +293,56 @@ impl FromStr for Layout {
                         layout.layers.insert(src.name.to_string(), src);
                     }
                     "keyboard" => {
-                        let [Atom(id)] = params else {
-                            return Err("Syntax error".to_string());
+                        let [Atom(id, span)] = params else {
+                            return Err(Error::new(r.span(), "Syntax error"));
                         };
-                        layout.keyboard = parser::parse(id)?;
+                        if !parser::KEYBOARDS.contains(id) {
+                            return Err(Error::new(*span, format!("Unknown keyboard {:?}", id))
+                                .with_code(Code::UnknownKeyboard)
+                                .with_suggestion(id, parser::KEYBOARDS.iter().copied()));
+                        }
+                        layout.keyboard = parser::parse(id).map_err(|e| Error::new(*span, e))?;
                         let src = Layer::from_keyboard(&layout.keyboard.source);
                         layout.layers.insert(src.name.to_string(), src);
                     }
                     "deflayer" => {
-                        let layer = Layer::from_def(params, i)?;
+                        let layer = Layer::from_def(params, i).map_err(|e| e.or_at(r.span()))?;
                         let keys = &layout.keyboard.source;
                         if layer.keys.len() != keys.len() {
-                            return Err(format!(
-                                "Syntax error: expected {}, found {} ({})",
-                                keys.len(),
-                                layer.keys.len(),
-                                name
+                            return Err(Error::new(
+                                r.span(),
+                                format!(
+                                    "Syntax error: expected {}, found {} ({})",
+                                    keys.len(),
+                                    layer.keys.len(),
+                                    name
+                                ),
                             ));
                         }
                         layout.layers.insert(layer.name.to_string(), layer);
                     }
                     "deflayermap" => {
-                        let layer = Layer::from_map(params, &layout.keyboard.source)?;
-                        let mut l = layout.layer_from(layer.parent, layer.name, i)?;
+                        let (layer, spans) = Layer::from_map(params, &layout.keyboard.source)
+                            .map_err(|e| e.or_at(r.span()))?;
+                        for (index, span) in spans {
+                            if let Some(previous) =
+                                entries.insert((layer.name.clone(), index), span)
+                            {
+                                layout.warnings.push(
+                                    Diagnostic::warning(
+                                        previous,
+                                        format!("Entry of layer {:?} is overwritten", layer.name),
+                                    )
+                                    .with_code(Code::OverwrittenEntry)
+                                    .with_note("a later deflayermap entry maps the same key"),
+                                );
+                            }
+                        }
+                        let mut l = layout
+                            .layer_from(layer.parent, layer.name, i)
+                            .map_err(|e| e.or_at(r.span()))?;
                         l.keys.extend(layer.keys);
+                        l.span = l.span.or(layer.span);
                         layout.layers.insert(l.name.to_string(), l);
                     }
                     "defalias" => {
@@ -223,9 +350,13 @@ impl FromStr for Layout {
                             params
                                 .chunks(2)
                                 .map(|x| {
-                                    let [Atom(name), expr] = x else {
-                                        return Err(format!("Syntax error: {:?}", x));
+                                    let [Atom(name, span), expr] = x else {
+                                        return Err(Error::new(
+                                            x[0].span(),
+                                            format!("Syntax error: {:?}", x),
+                                        ));
                                     };
+                                    alias_spans.push((name.to_string(), *span));
                                     let action = Action::from_expr(expr)?;
                                     Ok((name.to_string(), action))
                                 })
@@ -234,17 +365,20 @@ impl FromStr for Layout {
                     }
                     "defkeymap" => {
                         params.chunks(3).try_for_each(|x| {
-                            let [Atom(layer), Atom(keymap), act] = x else {
-                                return Err(format!("Syntax error: {:?}", x));
+                            let [Atom(layer, layer_span), Atom(keymap, keymap_span), act] = x
+                            else {
+                                return Err(Error::new(
+                                    x[0].span(),
+                                    format!("Syntax error: {:?}", x),
+                                ));
+                            };
+                            let Some(layer) = layout.layers.get_mut(*layer) else {
+                                return Err(layout.unknown_layer(layer, Some(*layer_span)));
                             };
-                            let layer = layout
-                                .layers
-                                .get_mut(*layer)
-                                .ok_or(format!("Layer {:?} not found", layer))?;
 
-                            let keymap: Keymap = keymap
-                                .parse()
-                                .map_err(|_| format!("Unknown keymap {:?}", keymap))?;
+                            let keymap: Keymap = keymap.parse().map_err(|_| {
+                                Error::new(*keymap_span, format!("Unknown keymap {:?}", keymap))
+                            })?;
 
                             let action = Action::from_expr(act)?;
 
@@ -255,54 +389,96 @@ impl FromStr for Layout {
                         })?;
                     }
                     "defoverride" => {
-                        let (name, parent, params) = Layer::get_name(params)?;
-                        let mut layer =
-                            layout.layer_from(parent.to_string(), name.to_string(), i)?;
+                        let span = Layer::name_span(params);
+                        let (name, parent, params) =
+                            Layer::get_name(params).map_err(|e| e.or_at(r.span()))?;
+                        let mut layer = layout
+                            .layer_from(parent.to_string(), name.to_string(), i)
+                            .map_err(|e| e.or_at(r.span()))?;
+                        layer.span = layer.span.or(span);
 
                         layer.overrides = params
                             .chunks(2)
                             .map(|x| {
-                                let [Atom(src), expr] = x else {
-                                    return Err(format!("Syntax error: {:?}", x));
+                                let [src_expr @ Atom(src, span), expr] = x else {
+                                    return Err(Error::new(
+                                        x[0].span(),
+                                        format!("Syntax error: {:?}", x),
+                                    ));
                                 };
-                                let Action::Multi(src) = Action::from_expr(&Atom(*src))? else {
-                                    return Err(format!("Expected hotkey, found {:?}", src));
+                                let err = |message: String| Error::new(*span, message);
+                                let Action::Multi(src) = Action::from_expr(src_expr)? else {
+                                    return Err(err(format!("Expected hotkey, found {:?}", src)));
                                 };
                                 let src: Vec<_> = src
                                     .into_iter()
                                     .map(|action| match action {
                                         Action::Tap(key) => Ok(key),
-                                        x => Err(format!("Expected tap, found {:?}", x)),
+                                        x => Err(err(format!("Expected tap, found {:?}", x))),
                                     })
                                     .collect::<Result<_, _>>()?;
 
                                 let [mods @ .., key] = src.as_slice() else {
-                                    return Err(format!("Expected hotkey, found {:?}", src));
+                                    return Err(err(format!("Expected hotkey, found {:?}", src)));
                                 };
 
-                                check_all_with(mods, |k| k.is_modifier())
-                                    .map_err(|k| format!("Expected modifier, found {:?}", k))?;
+                                let mods = Mods::from_keys(mods).map_err(|k| {
+                                    err(format!("Expected modifier, found {:?}", k))
+                                })?;
 
                                 if !layout.keyboard.source.contains_key(key) {
-                                    return Err(format!("Key {:?} not in source map", key));
+                                    return Err(err(format!("Key {:?} not in source map", key)));
                                 }
 
                                 Ok(Override {
-                                    key: key.clone(),
+                                    key: *key,
                                     action: Action::from_expr(expr)?,
-                                    mods: mods.to_vec(),
+                                    mods,
                                 })
                             })
                             .collect::<Result<_, _>>()?;
                         layout.layers.insert(layer.name.to_string(), layer);
                     }
                     "defvial" => layout.keyboard.vial = parse_vial(params)?,
+                    "defcfg" => layout.config.extend(params)?,
+                    "defmatrix" => layout.keyboard.matrix = parser::parse_matix(params)?,
+                    "defhands" => hands.extend(hands::parse(params)?),
+                    "defseq" => layout.sequences.extend(Sequence::from_def(params)?),
+                    "defcombo" => {
+                        let (combo, layers) = Combo::from_def(params, &layout.keyboard.source)
+                            .map_err(|e| e.or_at(r.span()))?;
+                        combo_layers.extend(layers);
+                        layout.combos.push(combo);
+                    }
+                    "include" => {
+                        return Err(Error::new(
+                            r.span(),
+                            "include is only supported in files loaded with Sources",
+                        ));
+                    }
 
-                    _ => return Err(format!("Unexpected {}", name)),
+                    _ => return Err(Error::new(name.span(), format!("Unexpected {}", name))),
                 }
                 Ok(())
             })?;
+        layout.check_references(&root, &aliases)?;
+        for (name, span) in &combo_layers {
+            if !layout.layers.contains_key(name) {
+                return Err(layout.unknown_layer(name, Some(*span)));
+            }
+        }
+        // Report cycles at the alias definition instead of a layer using it
+        for (name, span) in &alias_spans {
+            Action::Alias(name.clone())
+                .resolve_aliases(&aliases)
+                .map_err(|e| e.or_at(*span))?;
+        }
+        let warnings = lint::lint(expr, &root, &layout, &aliases);
+        layout.warnings.extend(warnings);
         layout.prepare_layers(&aliases)?;
+        layout.resolve_hands(&hands)?;
+        let warnings = layout.check_reachability();
+        layout.warnings.extend(warnings);
         Ok(layout)
     }
 }
@@ -312,33 +488,129 @@ fn resolve_unicode(
     lang: &Keymap,
     keymaps: &HashMap<Keymap, Action>,
 ) -> Result<Action, String> {
-    Ok(match action {
-        Action::Unicode(ch) => unicode(ch, lang, keymaps)?,
-        Action::TapHold(tap, hold) => Action::TapHold(
-            Box::new(resolve_unicode(tap, lang, keymaps)?),
-            Box::new(resolve_unicode(hold, lang, keymaps)?),
-        ),
-        Action::Multi(actions) => Action::Multi(
-            actions
-                .iter()
-                .map(|a| resolve_unicode(a, lang, keymaps))
-                .collect::<Result<_, _>>()?,
-        ),
-        Action::Sequence(actions) => Action::Sequence(
-            actions
-                .iter()
-                .map(|a| resolve_unicode(a, lang, keymaps))
-                .collect::<Result<_, _>>()?,
-        ),
-        other => other.clone(),
-    })
+    match action {
+        Action::Unicode(ch) => unicode(ch, lang, keymaps),
+        other => other.try_map(&mut |a| resolve_unicode(a, lang, keymaps)),
+    }
 }
 
-fn check_all_with<T, F>(src: &[T], predicate: F) -> Result<(), &T>
-where
-    F: Fn(&T) -> bool,
-{
-    src.iter()
-        .find(|item| !predicate(item))
-        .map_or(Ok(()), |bad| Err(bad))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(content: &str) -> Error {
+        content.parse::<Layout>().unwrap_err().remove(0)
+    }
+
+    #[test]
+    fn did_you_mean() {
+        let src = "(defsrc q w)\n";
+        let e = error(&format!("{}(deflayer default q bksp)", src));
+        assert_eq!(e.code, Some(Code::UnknownKey));
+        assert_eq!(e.help.as_deref(), Some("did you mean `bks`?"));
+
+        let e = error(&format!(
+            "{}(defalias copy (multi C q))\n(deflayer default q @cpy)",
+            src
+        ));
+        assert_eq!(
+            (e.code, e.span),
+            (Some(Code::UnknownAlias), Some(Span::new(61, 65)))
+        );
+        assert_eq!(e.help.as_deref(), Some("did you mean `copy`?"));
+
+        let e = error(&format!(
+            "{}(deflayer default q (layer-while-held defualt))",
+            src
+        ));
+        assert_eq!(e.code, Some(Code::UnknownLayer));
+        assert_eq!(e.help.as_deref(), Some("did you mean `default`?"));
+
+        let e = error(&format!("{}(deflayer default q (tap-hlod q w))", src));
+        assert_eq!(e.code, Some(Code::UnknownAction));
+        assert_eq!(e.help.as_deref(), Some("did you mean `tap-hold`?"));
+
+        let e = error(&format!(
+            "{}(deflayer default q (tap-hold q w :timout 150))",
+            src
+        ));
+        assert_eq!(e.code, Some(Code::UnknownOption));
+        assert_eq!(e.help.as_deref(), Some("did you mean `:timeout`?"));
+
+        let e = error("(keyboard imperial4)");
+        assert_eq!(e.help.as_deref(), Some("did you mean `imperial44`?"));
+    }
+
+    #[test]
+    fn combo_references() {
+        let e = error("(defsrc q w)\n(defcombo (q e) esc)");
+        assert_eq!(
+            (e.code, e.span),
+            (Some(Code::UnknownKey), Some(Span::new(26, 27)))
+        );
+
+        let e = error("(defsrc q w)\n(defcombo (q w) esc :layers (nv))\n(deflayer nav q w)");
+        assert_eq!(e.code, Some(Code::UnknownLayer));
+        assert_eq!(e.help.as_deref(), Some("did you mean `nav`?"));
+    }
+
+    #[test]
+    fn layer_action_dependencies() {
+        let e =
+            error("(defsrc q w)\n(deflayer default q (layer-with-mods nv C))\n(deflayer nav q w)");
+        assert_eq!(e.code, Some(Code::UnknownLayer));
+
+        let layout: Layout = "(defsrc q w e)
+            (deflayer default (layer-toggle nav) (layer-with-mods sym S) (layer-to game))
+            (deflayer nav _ _ _)
+            (deflayer sym _ _ _)
+            (deflayer game _ _ _)"
+            .parse()
+            .unwrap();
+        let mut deps = layout.layers["default"].get_dependencies();
+        deps.sort();
+        assert_eq!(deps, vec!["nav", "sym"]);
+    }
+
+    #[test]
+    fn alias_cycle() {
+        let e = error("(defsrc q)\n(defalias a @b b (macro q @c) c (tap-hold @a q))");
+        assert_eq!(e.code, Some(Code::AliasCycle));
+        assert_eq!(e.message, "Alias cycle: @a -> @b -> @c -> @a");
+        assert_eq!(e.span, Some(Span::new(21, 22)));
+    }
+
+    #[test]
+    fn aliases_resolved_everywhere() {
+        let layout: Layout = "(defsrc q w)
+            (defalias s (multi S q) m (macro q @s) ru (multi C @s))
+            (deflayer default @m w)
+            (defoverride default C-w @s)
+            (defkeymap default ru @ru)"
+            .parse()
+            .unwrap();
+        let has_alias = |a: &Action| format!("{:?}", a).contains("Alias");
+        let layer = &layout.layers["default"];
+        assert!(!layer.keys.values().any(has_alias));
+        assert!(!layer.overrides.iter().any(|o| has_alias(&o.action)));
+        assert!(!layout.keymaps.values().any(has_alias));
+    }
+
+    #[test]
+    fn one_shot_targets() {
+        let e = error("(defsrc q w)\n(deflayer default (one-shot a) w)");
+        assert_eq!(e.message, "Expected modifiers or layer-while-held, found a");
+        assert_eq!(e.span, Some(Span::new(31, 43)));
+
+        let e = error("(defsrc q w)\n(defalias m a)\n(deflayer default (one-shot @m) w)");
+        assert_eq!(e.message, "Expected modifiers or layer-while-held, found a");
+        assert_eq!(e.span, Some(Span::new(46, 59)));
+
+        "(defsrc q w)
+            (defalias m lsft-lctl)
+            (deflayer default (one-shot @m) (one-shot (layer-while-held nav)))
+            (deflayer nav _ _)"
+            .parse::<Layout>()
+            .unwrap();
+    }
 }
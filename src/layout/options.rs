@@ -0,0 +1,65 @@
+use s_expression::{Code, Error, Expr, Expr::*};
+
+/// Calls `f` with the name and value of every `:name value` pair. Names not
+/// in `names` are unknown options, so `f` only sees the listed ones
+pub fn options<'a, 'b>(
+    params: &'b [Expr<'a>],
+    names: &[&str],
+    mut f: impl FnMut(&str, &'b Expr<'a>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    for pair in params.chunks(2) {
+        let [Atom(name, span), value] = pair else {
+            return Err(Error::new(
+                pair[0].span(),
+                format!("Expected `:option value`, found {}", pair[0]),
+            ));
+        };
+        if !names.contains(name) {
+            return Err(Error::new(*span, format!("Unknown option {:?}", name))
+                .with_code(Code::UnknownOption)
+                .with_suggestion(name, names.iter().copied()));
+        }
+        f(name, value)?;
+    }
+    Ok(())
+}
+
+/// Value of a time option in milliseconds
+pub fn ms(value: &Expr) -> Result<u16, Error> {
+    value.atom()?.parse().map_err(|_| {
+        Error::new(
+            value.span(),
+            format!("Expected milliseconds, found {}", value),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Result<Vec<(String, u16)>, Error> {
+        let expr = s_expression::from_str(content).unwrap();
+        let mut values = Vec::new();
+        options(expr.list()?, &[":timeout", ":term"], |name, value| {
+            values.push((name.to_string(), ms(value)?));
+            Ok(())
+        })?;
+        Ok(values)
+    }
+
+    #[test]
+    fn pairs() {
+        assert_eq!(
+            parse("(:timeout 200 :term 50)").unwrap(),
+            vec![(":timeout".into(), 200), (":term".into(), 50)]
+        );
+        let e = parse("(:timeuot 200)").unwrap_err();
+        assert_eq!(e.code, Some(Code::UnknownOption));
+        assert_eq!(e.help.as_deref(), Some("did you mean `:timeout`?"));
+        let e = parse("(:timeout fast)").unwrap_err();
+        assert_eq!(e.message, "Expected milliseconds, found fast");
+        let e = parse("(:timeout)").unwrap_err();
+        assert_eq!(e.message, "Expected `:option value`, found :timeout");
+    }
+}
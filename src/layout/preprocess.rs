@@ -2,15 +2,17 @@ use std::collections::HashSet;
 
 use super::template;
 use super::unwrap::unwrap;
-use s_expression::Expr;
+use s_expression::{Error, Expr};
 
-pub fn preprocess<'a>(expr: &Expr<'a>) -> Result<Expr<'a>, String> {
+pub fn preprocess<'a>(expr: &Expr<'a>) -> Result<Expr<'a>, Error> {
     let mut templates = template::Templates::new();
     let root = expr.list()?;
 
-    root.iter().try_for_each(|item| -> Result<(), String> {
+    root.iter().try_for_each(|item| -> Result<(), Error> {
         let lst = item.list()?;
-        let name = lst.first().ok_or("Not found".to_string())?;
+        let name = lst
+            .first()
+            .ok_or(Error::new(item.span(), "Expected directive name"))?;
         if name.atom()? == "deftemplate" {
             templates.extend(template::deftemplate(lst[1..].to_vec())?)
         }
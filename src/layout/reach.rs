@@ -0,0 +1,260 @@
+use super::{Action, Layout};
+use s_expression::{Code, Diagnostic, Severity};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+impl Layout {
+    /// Checks the layer graph: every layer a key activates must be reachable
+    /// from `default`, every layer `layer-switch` makes the base layer must
+    /// have a way to switch back and every layer `layer-toggle` latches must
+    /// have a way to toggle off. Otherwise the board can get stuck in a
+    /// "trap" layer
+    pub(super) fn check_reachability(&self) -> Vec<Diagnostic> {
+        const DEFAULT: &str = "default";
+        if !self.layers.contains_key(DEFAULT) {
+            return Vec::new();
+        }
+        let mut from: HashMap<&str, &str> = HashMap::new();
+        let mut bases = vec![DEFAULT];
+        let mut queue = VecDeque::from([DEFAULT]);
+        while let Some(base) = queue.pop_front() {
+            for target in switch_targets(self, base) {
+                if target != DEFAULT && !from.contains_key(target) {
+                    from.insert(target, base);
+                    bases.push(target);
+                    queue.push_back(target);
+                }
+            }
+        }
+
+        // Warnings point at the definition of the layer, generated layers
+        // have none
+        let warning = |name: &str, message: String| match self.layers[name].span {
+            Some(span) => Diagnostic::warning(span, message),
+            None => Diagnostic {
+                severity: Severity::Warning,
+                ..Diagnostic::from(message)
+            },
+        };
+        let mut warnings = Vec::new();
+        for &base in &bases[1..] {
+            let mut seen = BTreeSet::from([base]);
+            let mut queue = VecDeque::from([base]);
+            let mut back = false;
+            while let Some(name) = queue.pop_front() {
+                for target in switch_targets(self, name) {
+                    back |= target == DEFAULT;
+                    if seen.insert(target) {
+                        queue.push_back(target);
+                    }
+                }
+            }
+            if !back {
+                let mut path = vec![base];
+                while let Some(prev) = from.get(path[path.len() - 1]) {
+                    path.push(prev);
+                }
+                path.reverse();
+                warnings.push(
+                    warning(base, format!("Layer {:?} has no way back to default", base))
+                        .with_code(Code::LayerTrap)
+                        .with_note(format!("layer-switch path: {}", path.join(" -> ")))
+                        .with_help(format!(
+                            "add (layer-switch default) to {:?} or a layer held from it",
+                            base
+                        )),
+                );
+            }
+        }
+
+        let reachable: BTreeSet<&str> = bases.iter().flat_map(|b| held_layers(self, b)).collect();
+        let mut toggled: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for &name in &reachable {
+            for target in layer_targets(self, name, Activation::Toggle) {
+                toggled.entry(target).or_default().insert(name);
+            }
+        }
+        for (&layer, from) in &toggled {
+            let off = held_layers(self, layer).into_iter().any(|name| {
+                layer_targets(self, name, Activation::Toggle).contains(layer)
+                    || self.layers[name].keys.values().any(clears_layers)
+            });
+            if !off {
+                let from: Vec<_> = from.iter().copied().collect();
+                warnings.push(
+                    warning(layer, format!("Layer {:?} has no way to toggle off", layer))
+                        .with_code(Code::LayerTrap)
+                        .with_note(format!("layer-toggle from: {}", from.join(", ")))
+                        .with_help(format!(
+                            "add (layer-toggle {}) to {:?} or a layer held from it",
+                            layer, layer
+                        )),
+                );
+            }
+        }
+
+        let mut activated: BTreeSet<&str> = BTreeSet::new();
+        for name in self.layers.keys() {
+            activated.extend(layer_targets(self, name, Activation::Held));
+            activated.extend(layer_targets(self, name, Activation::Toggle));
+            activated.extend(layer_targets(self, name, Activation::Switch));
+        }
+        for name in activated.difference(&reachable) {
+            warnings.push(
+                warning(
+                    name,
+                    format!("Layer {:?} is not reachable from default", name),
+                )
+                .with_code(Code::UnreachableLayer)
+                .with_note("it is only activated from layers that are never active"),
+            );
+        }
+        warnings
+    }
+}
+
+/// How an action activates a layer
+#[derive(Clone, Copy)]
+enum Activation {
+    /// On top of the active layers while the key is held
+    Held,
+    /// On top of the active layers until toggled off
+    Toggle,
+    /// As the base layer
+    Switch,
+}
+
+/// Layers that actions of the layer activate the `activation` way
+fn layer_targets<'a>(layout: &'a Layout, name: &str, activation: Activation) -> BTreeSet<&'a str> {
+    let Some(layer) = layout.layers.get(name) else {
+        return BTreeSet::new();
+    };
+    layer
+        .keys
+        .values()
+        .chain(layer.overrides.iter().map(|o| &o.action))
+        .flat_map(|a| match activation {
+            Activation::Held => a.layer_while_held_names(),
+            Activation::Toggle => a.layer_toggle_names(),
+            Activation::Switch => a.layer_switch_names(),
+        })
+        .filter(|n| layout.layers.contains_key(*n))
+        .collect()
+}
+
+/// Layers held or toggled on top of the base layer
+fn held_layers<'a>(layout: &'a Layout, base: &'a str) -> BTreeSet<&'a str> {
+    let mut seen = BTreeSet::from([base]);
+    let mut queue = VecDeque::from([base]);
+    while let Some(name) = queue.pop_front() {
+        let held = layer_targets(layout, name, Activation::Held);
+        for target in held
+            .into_iter()
+            .chain(layer_targets(layout, name, Activation::Toggle))
+        {
+            if seen.insert(target) {
+                queue.push_back(target);
+            }
+        }
+    }
+    seen
+}
+
+/// Base layers `layer-switch` can set from the base layer
+fn switch_targets<'a>(layout: &'a Layout, base: &'a str) -> BTreeSet<&'a str> {
+    held_layers(layout, base)
+        .into_iter()
+        .flat_map(|name| layer_targets(layout, name, Activation::Switch))
+        .collect()
+}
+
+/// Whether the action turns off every layer but its target, like `layer-to`
+fn clears_layers(action: &Action) -> bool {
+    match action {
+        Action::LayerTo(_) => true,
+        Action::OneShot(action, _) => clears_layers(action),
+        Action::TapHold(tap, hold, _) => clears_layers(tap) || clears_layers(hold),
+        Action::TapDance(dance) => dance.branches().any(clears_layers),
+        Action::Multi(actions) | Action::Sequence(actions) => actions.iter().any(clears_layers),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(content: &str) -> Vec<Option<&str>> {
+        let layout: Layout = content.parse().unwrap();
+        layout
+            .check_reachability()
+            .into_iter()
+            .map(|w| w.span.map(|s| &content[s.start..s.end]))
+            .collect()
+    }
+
+    fn codes(content: &str) -> Vec<(Code, String)> {
+        let layout: Layout = content.parse().unwrap();
+        layout
+            .check_reachability()
+            .into_iter()
+            .map(|w| (w.code.unwrap(), w.message))
+            .collect()
+    }
+
+    #[test]
+    fn traps() {
+        let src = "(defsrc q w e)\n";
+        assert!(
+            codes(&format!(
+                "{}(deflayer default (layer-switch game) (layer-while-held nav) e)
+                (deflayer game _ (layer-while-held nav) _)
+                (deflayer nav _ _ (layer-switch default))",
+                src
+            ))
+            .is_empty()
+        );
+        assert_eq!(
+            codes(&format!(
+                "{}(deflayer default (layer-switch game) w e)
+                (deflayer game _ (layer-while-held orphan) _)
+                (deflayer (orphan game) _ _ _)
+                (deflayer (lost orphan) _ _ (layer-while-held hidden))
+                (deflayer (hidden orphan) _ _ _)",
+                src
+            )),
+            vec![
+                (
+                    Code::LayerTrap,
+                    "Layer \"game\" has no way back to default".into()
+                ),
+                (
+                    Code::UnreachableLayer,
+                    "Layer \"hidden\" is not reachable from default".into()
+                ),
+            ]
+        );
+        assert_eq!(
+            codes(&format!(
+                "{}(deflayer default (layer-toggle game) (layer-tap-toggle nav) (layer-toggle num))
+                (deflayer game (layer-switch default) w e)
+                (deflayer nav _ _ _)
+                (deflayer num (layer-to default) _ _)",
+                src
+            )),
+            vec![(
+                Code::LayerTrap,
+                "Layer \"game\" has no way to toggle off".into()
+            )]
+        );
+        assert_eq!(
+            spans(
+                "(defsrc q w)
+                (deflayer default (layer-switch game) w)
+                (deflayermap (game default) w _)
+                (deflayermap (lost default) q (layer-while-held hidden))
+                (deflayer (hidden lost) _ _)"
+            ),
+            vec![Some("game"), Some("hidden")]
+        );
+    }
+}
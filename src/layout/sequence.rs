@@ -0,0 +1,40 @@
+use super::Action;
+use keys::keys::Key;
+use s_expression::{Error, Expr, Expr::*, Span};
+
+/// Action run after `leader` and the keys, `(defseq (g c) "git commit")`
+#[derive(Debug, Clone)]
+pub struct Sequence {
+    pub keys: Vec<Key>,
+    pub action: Action,
+    pub span: Span,
+}
+
+impl Sequence {
+    /// Parses the `(keys ...) action` pairs of a `defseq` form
+    pub fn from_def(params: &[Expr]) -> Result<Vec<Self>, Error> {
+        params
+            .chunks(2)
+            .map(|pair| {
+                let [List(keys, span), action] = pair else {
+                    return Err(Error::new(
+                        pair[0].span(),
+                        format!("Expected `(keys ...) action`, found {}", pair[0]),
+                    ));
+                };
+                if keys.is_empty() {
+                    return Err(Error::new(*span, "Expected at least one key"));
+                }
+                let keys = keys
+                    .iter()
+                    .map(|k| parser::parse_key(k.atom()?, k.span()))
+                    .collect::<Result<_, _>>()?;
+                Ok(Sequence {
+                    keys,
+                    action: Action::from_expr(action)?,
+                    span: *span,
+                })
+            })
+            .collect()
+    }
+}
@@ -1,4 +1,7 @@
-use s_expression::Expr::{self, *};
+use s_expression::{
+    Error,
+    Expr::{self, *},
+};
 use std::collections::HashMap;
 
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -6,22 +9,31 @@ pub struct Template<'a>(Vec<&'a str>, Expr<'a>);
 
 pub type Templates<'a> = HashMap<&'a str, Template<'a>>;
 
-pub fn deftemplate<'a>(list: Vec<Expr<'a>>) -> Result<Templates<'a>, String> {
+pub fn deftemplate<'a>(list: Vec<Expr<'a>>) -> Result<Templates<'a>, Error> {
     let mut templates: HashMap<&'a str, Template<'a>> = HashMap::new();
 
     list.chunks(3).try_for_each(|r| {
-        let [Atom(x), List(args), value] = r else {
-            return Err(format!("SyntaxError: {:?}", r));
+        let [Atom(x, _), List(args, _), value] = r else {
+            return Err(Error::new(
+                r[0].span().join(r[r.len() - 1].span()),
+                format!("SyntaxError: {:?}", r),
+            ));
         };
         let names = args
             .iter()
             .try_fold(Vec::with_capacity(args.len()), |mut acc, expr| {
                 let x = expr.atom()?;
                 if !x.starts_with("$") {
-                    return Err(format!("Argument should start from $, found {:?}", x));
+                    return Err(Error::new(
+                        expr.span(),
+                        format!("Argument should start from $, found {:?}", x),
+                    ));
                 }
                 if acc.contains(&x) {
-                    return Err(format!("Argument {:?} already defined", x));
+                    return Err(Error::new(
+                        expr.span(),
+                        format!("Argument {:?} already defined", x),
+                    ));
                 }
                 acc.push(x);
                 Ok(acc)
@@ -34,14 +46,14 @@ pub fn deftemplate<'a>(list: Vec<Expr<'a>>) -> Result<Templates<'a>, String> {
 }
 
 pub fn expand<'a>(expr: &Expr<'a>, templates: &Templates<'a>) -> Expr<'a> {
-    let List(list) = expr else {
+    let List(list, span) = expr else {
         return expr.clone();
     };
-    let Some(Atom(name)) = list.first() else {
-        return List(list.iter().map(|e| expand(e, templates)).collect());
+    let Some(Atom(name, _)) = list.first() else {
+        return List(list.iter().map(|e| expand(e, templates)).collect(), *span);
     };
     let Some(template) = templates.get(name) else {
-        return List(list.iter().map(|e| expand(e, templates)).collect());
+        return List(list.iter().map(|e| expand(e, templates)).collect(), *span);
     };
     let args = &list[1..];
     let mut env = HashMap::new();
@@ -61,10 +73,15 @@ pub fn expand<'a>(expr: &Expr<'a>, templates: &Templates<'a>) -> Expr<'a> {
         if extra_expr_list.len() == 1 {
             extra_expr_list[0].clone()
         } else {
-            List(extra_expr_list)
+            let span = extra_args
+                .iter()
+                .map(Expr::span)
+                .reduce(|a, b| a.join(b))
+                .unwrap_or(*span);
+            List(extra_expr_list, span)
         },
     );
-    return substitute(&body, &env, templates);
+    substitute(body, &env, templates)
 }
 
 fn substitute<'a>(
@@ -73,12 +90,13 @@ fn substitute<'a>(
     templates: &Templates<'a>,
 ) -> Expr<'a> {
     match expr {
-        Expr::Atom(a) => env.get(a).cloned().unwrap_or_else(|| Expr::Atom(a)),
+        Expr::Atom(a, _) => env.get(a).cloned().unwrap_or_else(|| expr.clone()),
+        Expr::Str(..) => expr.clone(),
 
-        Expr::List(list) => {
+        Expr::List(list, span) => {
             let expanded_list: Vec<Expr> =
                 list.iter().map(|e| substitute(e, env, templates)).collect();
-            expand(&Expr::List(expanded_list), templates)
+            expand(&Expr::List(expanded_list, *span), templates)
         }
     }
 }
@@ -95,7 +113,10 @@ mod tests {
 
         assert_eq!(
             templates,
-            HashMap::from([("a", Template(vec!["$b", "$c"], Atom("$c")))]),
+            HashMap::from([(
+                "a",
+                Template(vec!["$b", "$c"], Atom("$c", Default::default()))
+            )]),
         );
     }
 
@@ -1,38 +1,46 @@
 use super::{Action, Keymap};
-use s_expression::Expr::*;
+use s_expression::{Error, Expr::*};
 use std::collections::HashMap;
 
 use std::sync::OnceLock;
 
-static LANG_CHARS: OnceLock<Result<HashMap<Keymap, HashMap<char, Action>>, String>> =
-    OnceLock::new();
+type LangChars = HashMap<Keymap, HashMap<char, Action>>;
 
-fn load_lang_chars() -> Result<HashMap<Keymap, HashMap<char, Action>>, String> {
-    let content = format!("({})", include_str!("unicode.rkl"));
-    let expr = s_expression::from_str(&content).map_err(|_| "Parse error")?;
+static LANG_CHARS: OnceLock<Result<LangChars, String>> = OnceLock::new();
+
+fn load_lang_chars() -> Result<LangChars, String> {
+    let content = include_str!("unicode.rkl");
+    load_lang_chars_from(content).map_err(|e| e.render("unicode.rkl", content))
+}
+
+fn load_lang_chars_from(content: &str) -> Result<LangChars, Error> {
+    let expr =
+        s_expression::from_str_list(content).map_err(|errors| Error::from(errors[0].clone()))?;
     let list = expr.list()?;
 
-    list.iter().try_fold(
-        HashMap::<Keymap, HashMap<char, Action>>::with_capacity(list.len()),
-        |mut acc, l| {
+    list.iter()
+        .try_fold(LangChars::with_capacity(list.len()), |mut acc, l| {
             let list = l.list()?;
-            let [Atom(name), params @ ..] = list.as_slice() else {
-                return Err(format!("Name of {} not found", l));
+            let [Atom(name, name_span), params @ ..] = list.as_slice() else {
+                return Err(Error::new(l.span(), format!("Name of {} not found", l)));
             };
             if *name != "defunicode" {
-                return Err(format!("Unknown {:?}", name));
+                return Err(Error::new(*name_span, format!("Unknown {:?}", name)));
             }
 
-            let [Atom(keymap), params @ ..] = params else {
-                return Err(format!("Expected atom, found {:?}", params));
+            let [Atom(keymap, keymap_span), params @ ..] = params else {
+                return Err(Error::new(
+                    l.span(),
+                    format!("Expected atom, found {:?}", params),
+                ));
             };
 
             let keymap = keymap
                 .parse::<Keymap>()
-                .map_err(|_| format!("Keymap {:?} not found", keymap))?;
+                .map_err(|_| Error::new(*keymap_span, format!("Keymap {:?} not found", keymap)))?;
 
             if params.len() % 2 != 0 {
-                return Err("Syntax error".to_string());
+                return Err(Error::new(l.span(), "Syntax error"));
             }
 
             acc.insert(
@@ -40,29 +48,27 @@ fn load_lang_chars() -> Result<HashMap<Keymap, HashMap<char, Action>>, String> {
                 params.chunks(2).try_fold(
                     HashMap::with_capacity(params.len() / 2),
                     |mut acc, c| {
-                        let [Atom(ch), action] = c else {
-                            unreachable!()
-                        };
-
-                        let ch = match *ch {
-                            "lb" => '(',
-                            "rb" => ')',
-                            _ => ch
-                                .chars()
-                                .next()
-                                .ok_or(format!("Expected char, found {:?}", ch))?,
-                        };
+                        let [ch, action] = c else { unreachable!() };
+
+                        let ch = match ch {
+                            Atom(s, _) if s.chars().count() == 1 => s.chars().next(),
+                            Str(s, _) if s.chars().count() == 1 => s.chars().next(),
+                            _ => None,
+                        }
+                        .ok_or(Error::new(
+                            ch.span(),
+                            format!("Expected char, found {}", ch),
+                        ))?;
 
                         let action = Action::from_expr(action)?;
                         acc.insert(ch, action);
-                        Ok::<_, String>(acc)
+                        Ok::<_, Error>(acc)
                     },
                 )?,
             );
 
             Ok(acc)
-        },
-    )
+        })
 }
 
 pub fn unicode(
@@ -75,23 +81,61 @@ pub fn unicode(
         .as_ref()
         .map_err(|e| e.clone())?;
 
-    if let Some(chars) = lang_chars.get(lang) {
-        if let Some(a) = chars.get(ch) {
-            return Ok(a.clone());
-        }
+    if let Some(chars) = lang_chars.get(lang)
+        && let Some(a) = chars.get(ch)
+    {
+        return Ok(a.clone());
     };
 
     if let Some(lang_hotkey) = keymaps.get(lang) {
         for (lang, action) in keymaps.iter() {
-            if let Some(chars) = lang_chars.get(lang) {
-                if let Some(a) = chars.get(ch) {
-                    return Ok(Action::Sequence(
-                        [action.clone(), a.clone(), lang_hotkey.clone()].to_vec(),
-                    ));
-                }
+            if let Some(chars) = lang_chars.get(lang)
+                && let Some(a) = chars.get(ch)
+            {
+                return Ok(Action::Sequence(
+                    [action.clone(), a.clone(), lang_hotkey.clone()].to_vec(),
+                ));
             }
         }
     }
 
     Ok(Action::Unicode(*ch))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keys::keys::Key;
+    use s_expression::Span;
+
+    #[test]
+    fn iso_keys() {
+        let chars = load_lang_chars_from(
+            r#"(defunicode en
+                | S-nubs
+                ~ S-nuhs
+                ¥ yen)"#,
+        )
+        .unwrap();
+        let en = &chars[&Keymap::En];
+        let Action::Multi(shifted) = &en[&'|'] else {
+            panic!("{:?}", en[&'|']);
+        };
+        assert!(matches!(
+            shifted.as_slice(),
+            [
+                Action::Tap(Key::LeftShift),
+                Action::Tap(Key::NonUsBackslash)
+            ]
+        ));
+        assert!(matches!(en[&'¥'], Action::Tap(Key::IntlYen)));
+        assert!(load_lang_chars().is_ok());
+    }
+
+    #[test]
+    fn multi_char_atom() {
+        let e = load_lang_chars_from("(defunicode en lb S-9)").unwrap_err();
+        assert_eq!(e.message, "Expected char, found lb");
+        assert_eq!(e.span, Some(Span::new(15, 17)));
+    }
+}
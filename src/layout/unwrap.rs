@@ -3,16 +3,16 @@ use std::collections::HashSet;
 use s_expression::Expr::{self, *};
 
 pub fn unwrap<'a>(expr: &Expr<'a>, ignore: Option<&HashSet<&str>>) -> Expr<'a> {
-    let List(list) = expr else {
+    let List(list, span) = expr else {
         return expr.clone();
     };
     List(
         list.iter()
             .flat_map(|item| {
-                let List(list) = item else {
+                let List(list, _) = item else {
                     return vec![unwrap(item, ignore)];
                 };
-                let [Atom(name), inner @ ..] = list.as_slice() else {
+                let [Atom(name, _), inner @ ..] = list.as_slice() else {
                     return vec![unwrap(item, ignore)];
                 };
                 match *name {
@@ -22,6 +22,7 @@ pub fn unwrap<'a>(expr: &Expr<'a>, ignore: Option<&HashSet<&str>>) -> Expr<'a> {
                 }
             })
             .collect(),
+        *span,
     )
 }
 
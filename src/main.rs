@@ -1,7 +1,9 @@
+mod format;
 mod layout;
 mod transform;
 
-use layout::Layout;
+use layout::Sources;
+use std::path::Path;
 
 use argh::FromArgs;
 
@@ -10,7 +12,10 @@ use argh::FromArgs;
 struct Args {
     /// input file path
     #[argh(positional)]
-    file: String,
+    file: Option<String>,
+
+    #[argh(subcommand)]
+    command: Option<Command>,
 
     /// apply keymap to vial
     #[argh(switch)]
@@ -19,20 +24,80 @@ struct Args {
     /// generate kanata config
     #[argh(option)]
     kanata: Option<String>,
+
+    /// flash with vial even if a layer has no way back to default
+    #[argh(switch)]
+    force: bool,
+
+    /// treat lint warnings as errors
+    #[argh(switch)]
+    deny_warnings: bool,
+}
+
+#[derive(FromArgs, Debug)]
+#[argh(subcommand)]
+enum Command {
+    Fmt(Fmt),
+}
+
+#[derive(FromArgs, Debug)]
+/// format a layout file in place
+#[argh(subcommand, name = "fmt")]
+struct Fmt {
+    /// input file path
+    #[argh(positional)]
+    file: String,
+
+    /// only check that the file is formatted
+    #[argh(switch)]
+    check: bool,
 }
 
-fn main() -> Result<(), String> {
+fn main() {
     env_logger::init();
 
     let args: Args = argh::from_env();
 
-    let content = std::fs::read_to_string(args.file).map_err(|e| e.to_string())?;
-    let layout: Layout = content.parse()?;
+    if let Err(e) = run(args) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn render(errors: Vec<s_expression::Error>, file: &str, content: &str) -> String {
+    errors
+        .iter()
+        .map(|e| e.render(file, content))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn run(args: Args) -> Result<(), String> {
+    if let Some(Command::Fmt(fmt)) = args.command {
+        return run_fmt(fmt);
+    }
+    let file = args.file.ok_or("Input file path expected")?;
+    let mut sources = Sources::default();
+    let layout = sources
+        .load(Path::new(&file))
+        .and_then(|_| sources.layout())
+        .map_err(|errors| sources.render(&errors))?;
+    if !layout.warnings.is_empty() {
+        eprintln!("{}\n", sources.render(&layout.warnings));
+        if args.deny_warnings {
+            return Err(format!(
+                "error: aborting due to {} warning(s)",
+                layout.warnings.len()
+            ));
+        }
+    }
 
     if args.vial {
-        layout.vial(None)?;
+        layout
+            .vial(None, args.force)
+            .map_err(|e| sources.render(&[e]))?;
     } else if let Some(a) = args.kanata {
-        let text = layout.kanata()?;
+        let text = layout.kanata().map_err(|e| sources.render(&[e]))?;
         match a.as_str() {
             "-" => println!("{}", text),
             filename => {
@@ -46,3 +111,18 @@ fn main() -> Result<(), String> {
 
     Ok(())
 }
+
+fn run_fmt(args: Fmt) -> Result<(), String> {
+    let content = std::fs::read_to_string(&args.file).map_err(|e| e.to_string())?;
+    let formatted =
+        format::format(&content).map_err(|errors| render(errors, &args.file, &content))?;
+    if formatted == content {
+        return Ok(());
+    }
+    if args.check {
+        return Err(format!("{} is not formatted", args.file));
+    }
+    std::fs::write(&args.file, formatted).map_err(|e| e.to_string())?;
+    println!("Formatted {}", args.file);
+    Ok(())
+}
@@ -1 +0,0 @@
-pub const TAP_HOLD_MS: usize = 200;
@@ -1,3 +1,4 @@
+use s_expression::{Code, Error};
 use std::{
     collections::{BinaryHeap, HashMap},
     fmt::Debug,
@@ -8,7 +9,7 @@ pub struct Node<'a> {
     pub weight: usize,
     pub deps: Vec<&'a str>,
 }
-pub fn priority_topo_sort<'a>(graph: &HashMap<&'a str, Node<'a>>) -> Result<Vec<&'a str>, String> {
+pub fn priority_topo_sort<'a>(graph: &HashMap<&'a str, Node<'a>>) -> Result<Vec<&'a str>, Error> {
     let mut in_degree: HashMap<&str, usize> = HashMap::new();
     let mut reverse_graph: HashMap<&str, Vec<&str>> = HashMap::new();
 
@@ -41,7 +42,7 @@ pub fn priority_topo_sort<'a>(graph: &HashMap<&'a str, Node<'a>>) -> Result<Vec<
             for &dep in dependents {
                 let deg = in_degree
                     .get_mut(dep)
-                    .ok_or(format!("Unknown dependency {:?}", dep))?;
+                    .ok_or(Error::from(format!("Unknown dependency {:?}", dep)))?;
                 *deg -= 1;
 
                 if *deg == 0 {
@@ -53,7 +54,18 @@ pub fn priority_topo_sort<'a>(graph: &HashMap<&'a str, Node<'a>>) -> Result<Vec<
     }
 
     if result.len() != graph.len() {
-        return Err("Cycle detected".into());
+        let mut cycle: Vec<_> = in_degree
+            .iter()
+            .filter(|(_, deg)| **deg > 0)
+            .map(|(name, _)| *name)
+            .collect();
+        cycle.sort();
+        return Err(Error::from("Cycle detected")
+            .with_code(Code::LayerCycle)
+            .with_note(format!(
+                "layers depending on each other: {}",
+                cycle.join(", ")
+            )));
     }
 
     Ok(result)
@@ -1,8 +1,15 @@
 use std::collections::{HashMap, HashSet};
 
-use super::config;
-use crate::layout::{Action, Layout};
-use keys::keys::Key;
+use crate::layout::{
+    Action, CAPS_WORD_SHIFTED, Config, Direction, Flavor, Layout, MouseOptions, TapHoldOptions,
+};
+use keys::{keys::Key, mods::Mods};
+use s_expression::{Code, Error};
+
+/// Mouse movement defaults: interval in ms and distance in pixels
+const MOUSE_MOVE: (u16, u16) = (10, 8);
+/// Wheel defaults: interval in ms and distance, 120 is one notch
+const MOUSE_WHEEL: (u16, u16) = (50, 120);
 
 fn sorted<'a, K, V, I: Ord, R, F: Fn(&'a K, &'a V) -> (I, R)>(
     map: &'a HashMap<K, V>,
@@ -17,13 +24,28 @@ fn sorted<'a, K, V, I: Ord, R, F: Fn(&'a K, &'a V) -> (I, R)>(
 }
 
 impl Layout {
-    pub fn kanata(&self) -> Result<String, String> {
+    pub fn kanata(&self) -> Result<String, Error> {
         let mut lines = vec![];
+        let config = &self.config;
+        let timing = config.kanata();
+
+        let mut options = self.config.kanata_options.clone();
+        // defchordsv2 is rejected without it
+        if !self.combos.is_empty() && !options.iter().any(|(n, _)| n == "concurrent-tap-hold") {
+            options.push(("concurrent-tap-hold".into(), "yes".into()));
+        }
+        if !options.is_empty() {
+            let options: Vec<_> = options
+                .iter()
+                .map(|(name, value)| format!("\t{} {}", name, value))
+                .collect();
+            lines.push(format!("(defcfg\n{}\n)", options.join("\n")));
+        }
 
         let mut source = sorted(&self.keyboard.source, |k, i| (i, k))
             .into_iter()
             .map(key_to_kanata)
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>, _>>()?;
         source.insert(0, "defsrc".into());
         lines.push(format!("({})", source.join(" ")));
 
@@ -44,11 +66,14 @@ impl Layout {
                     .iter()
                     .filter_map(|(i, a)| {
                         let Some(key) = source.get(i) else {
-                            return Some(Err(format!("Key {:?} not found", i)));
+                            return Some(Err(format!("Key {:?} not found", i).into()));
                         };
 
-                        let key = key_to_kanata(key);
-                        match action_to_kanata(a) {
+                        let key = match key_to_kanata(key) {
+                            Ok(key) => key,
+                            Err(e) => return Some(Err(e)),
+                        };
+                        match action_to_kanata(a, config) {
                             Ok(action) => {
                                 if action != key {
                                     Some(Ok(format!("\t{} {}", key, action)))
@@ -57,7 +82,7 @@ impl Layout {
                                 }
                             }
 
-                            e => Some(e),
+                            Err(e) => Some(Err(l.locate(e))),
                         }
                     })
                     .collect::<Result<_, _>>()?;
@@ -68,7 +93,7 @@ impl Layout {
                 ));
 
                 l.overrides.iter().try_for_each(|o| {
-                    let action = action_to_kanata(&o.action)?;
+                    let action = action_to_kanata(&o.action, config).map_err(|e| l.locate(e))?;
                     let action = if action.starts_with("(") {
                         action
                     } else {
@@ -77,34 +102,31 @@ impl Layout {
                     let res = format!(
                         "({} {}) {}",
                         o.mods
-                            .iter()
-                            .map(key_to_kanata)
-                            .collect::<Vec<_>>()
+                            .keys()
+                            .map(|k| key_to_kanata(&k))
+                            .collect::<Result<Vec<_>, _>>()?
                             .join(" "),
-                        key_to_kanata(&o.key),
+                        key_to_kanata(&o.key)?,
                         action
                     );
                     overrides
                         .entry(res)
                         .or_insert_with(|| HashSet::with_capacity(1))
                         .insert(l.name.clone());
-                    Ok::<_, String>(())
+                    Ok::<_, Error>(())
                 })?;
 
-                Ok::<_, String>(())
+                Ok::<_, Error>(())
             })?;
 
-        if overrides.len() > 0 {
-            let all_layers = HashSet::from_iter(self.layers.iter().map(|(n, _)| n.clone()));
+        if !overrides.is_empty() {
+            let all_layers = HashSet::from_iter(self.layers.keys().cloned());
             lines.push(format!(
                 "(defoverridesv2 \n{}\n)",
                 overrides
                     .iter()
                     .map(|(o, l)| {
-                        let layers = all_layers
-                            .difference(l)
-                            .map(|s| s.clone())
-                            .collect::<Vec<_>>();
+                        let layers = all_layers.difference(l).cloned().collect::<Vec<_>>();
                         format!("\t{} () ({})", o, layers.join(" "))
                     })
                     .collect::<Vec<_>>()
@@ -112,23 +134,138 @@ impl Layout {
             ));
         }
 
-        return Ok(lines.join("\n\n"));
+        if !self.combos.is_empty() {
+            let combos = self
+                .combos
+                .iter()
+                .map(|combo| {
+                    let keys = combo
+                        .keys
+                        .iter()
+                        .map(key_to_kanata)
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let mut disabled: Vec<_> = self
+                        .layers
+                        .keys()
+                        .filter(|l| !combo.layers.is_empty() && !combo.layers.contains(l))
+                        .map(String::as_str)
+                        .collect();
+                    disabled.sort();
+                    Ok(format!(
+                        "\t({}) {} {} all-released ({})",
+                        keys.join(" "),
+                        action_to_kanata(&combo.action, config)?,
+                        combo.timeout.unwrap_or(timing.combo_term()),
+                        disabled.join(" ")
+                    ))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            lines.push(format!("(defchordsv2\n{}\n)", combos.join("\n")));
+        }
+
+        if !self.sequences.is_empty() {
+            let mut keys = Vec::new();
+            let mut sequences = Vec::new();
+            for (i, sequence) in self.sequences.iter().enumerate() {
+                let action = action_to_kanata(&sequence.action, config)
+                    .map_err(|e| e.or_at(sequence.span))?;
+                let seq = sequence
+                    .keys
+                    .iter()
+                    .map(key_to_kanata)
+                    .collect::<Result<Vec<_>, _>>()?;
+                keys.push(format!("\tseq-{} {}", i, action));
+                sequences.push(format!("\tseq-{} ({})", i, seq.join(" ")));
+            }
+            lines.push(format!("(defvirtualkeys\n{}\n)", keys.join("\n")));
+            lines.push(format!("(defseq\n{}\n)", sequences.join("\n")));
+        }
+
+        Ok(lines.join("\n\n"))
     }
 }
 
-fn action_to_kanata(action: &Action) -> Result<String, String> {
+fn action_to_kanata(action: &Action, config: &Config) -> Result<String, Error> {
+    let timing = config.kanata();
     Ok(match action {
-        Action::Tap(key) => key_to_kanata(key),
+        Action::Tap(key) => match mouse_key(key) {
+            Some(action) => action,
+            None => key_to_kanata(key)?,
+        },
         Action::Transparent => "_".into(),
         Action::NoAction => "XX".into(),
         Action::Alias(a) => format!("@{}", a),
-        Action::TapHold(tap, hold) => format!(
-            "(tap-hold {} {} {} {})",
-            config::TAP_HOLD_MS,
-            config::TAP_HOLD_MS,
-            action_to_kanata(tap)?,
-            action_to_kanata(hold)?,
-        ),
+        Action::TapHold(tap, hold, options) => {
+            let name = match (&options.flavor, options.tap_keys.is_empty()) {
+                (Flavor::Timeout, true) => "tap-hold",
+                (Flavor::Press, _) => "tap-hold-press",
+                (Flavor::Release, true) => "tap-hold-release",
+                (_, false) => "tap-hold-release-keys",
+            };
+            let mut params = vec![
+                options
+                    .tap_timeout
+                    .unwrap_or(timing.tap_timeout())
+                    .to_string(),
+                options.timeout.unwrap_or(timing.hold_timeout()).to_string(),
+                action_to_kanata(tap, config)?,
+                action_to_kanata(hold, config)?,
+            ];
+            if !options.tap_keys.is_empty() {
+                let keys = options
+                    .tap_keys
+                    .iter()
+                    .map(key_to_kanata)
+                    .collect::<Result<Vec<_>, _>>()?;
+                params.push(format!("({})", keys.join(" ")));
+            }
+            format!("({} {})", name, params.join(" "))
+        }
+        Action::OneShot(action, options) => {
+            let one_shot = format!(
+                "({} {} {})",
+                if options.on_release {
+                    "one-shot-release"
+                } else {
+                    "one-shot-press"
+                },
+                options.timeout.unwrap_or(timing.one_shot_timeout()),
+                action_to_kanata(action, config)?
+            );
+            if options.pause_on_layer {
+                format!("(multi {} (one-shot-pause-processing 5))", one_shot)
+            } else {
+                one_shot
+            }
+        }
+        Action::TapDance(dance) => {
+            let term = dance.term.unwrap_or(timing.tapping_term());
+            let branch = |tap: &Option<Box<Action>>, hold: &Option<Box<Action>>| {
+                let tap = tap.as_deref().cloned().unwrap_or(Action::NoAction);
+                match hold {
+                    Some(hold) => Action::TapHold(
+                        Box::new(tap),
+                        hold.clone(),
+                        TapHoldOptions {
+                            timeout: dance.term,
+                            ..Default::default()
+                        },
+                    ),
+                    None => tap,
+                }
+            };
+            let single = action_to_kanata(&branch(&dance.tap, &dance.hold), config)?;
+            if dance.double_tap.is_none() && dance.tap_hold.is_none() {
+                return Ok(single);
+            }
+            let double = action_to_kanata(&branch(&dance.double_tap, &dance.tap_hold), config)?;
+            let name = if dance.eager {
+                "tap-dance-eager"
+            } else {
+                "tap-dance"
+            };
+            format!("({} {} ({} {}))", name, term, single, double)
+        }
         Action::Multi(elems) => {
             let taps: Vec<_> = elems
                 .iter()
@@ -142,16 +279,17 @@ fn action_to_kanata(action: &Action) -> Result<String, String> {
                 .collect();
             if taps.len() == elems.len() {
                 let (mods, keys): (Vec<&Key>, Vec<&Key>) =
-                    taps.iter().partition(|k| k.is_modifier());
-                if let [tap] = keys.as_slice() {
-                    if let Some(mods) = format_mods(mods.as_slice()) {
-                        return Ok(format!("{}-{}", mods, key_to_kanata(tap)));
-                    }
+                    taps.into_iter().partition(|k| k.is_modifier());
+                if let ([tap], Ok(mods)) = (keys.as_slice(), Mods::from_keys(mods))
+                    && !mods.is_empty()
+                    && mouse_key(tap).is_none()
+                {
+                    return Ok(format!("{}-{}", mods.kanata(), key_to_kanata(tap)?));
                 }
             }
             let res = elems
                 .iter()
-                .map(action_to_kanata)
+                .map(|a| action_to_kanata(a, config))
                 .collect::<Result<Vec<_>, _>>()?;
             let (actions, keys): (Vec<String>, Vec<String>) =
                 res.into_iter().partition(|s| s.starts_with("("));
@@ -159,199 +297,414 @@ fn action_to_kanata(action: &Action) -> Result<String, String> {
             format!("(multi {} {})", actions.join(" "), keys.join(" "))
         }
         Action::LayerWhileHeld(l) => format!("(layer-while-held {})", l),
-        Action::LayerSwitch(l) => format!("(layer-switch {})", l),
-        Action::Unicode(_) => todo!(),
+        Action::LayerSwitch(l) | Action::LayerTo(l) => format!("(layer-switch {})", l),
+        // kanata's layer-toggle is an old name of layer-while-held
+        Action::LayerToggle(l) | Action::LayerTapToggle(l) => {
+            return Err(
+                Error::from(format!("Kanata has no latching layer toggle, {}", action))
+                    .with_code(Code::Unsupported)
+                    .with_note("kanata's layer-toggle only holds the layer, like layer-while-held")
+                    .with_help(format!(
+                        "use (layer-switch {}) with a (layer-switch default) key in {}",
+                        l, l
+                    )),
+            );
+        }
+        Action::CapsWord if config.caps_word_keys.is_empty() => {
+            format!("(caps-word {})", timing.caps_word_timeout())
+        }
+        Action::CapsWord => {
+            let shifted = CAPS_WORD_SHIFTED
+                .iter()
+                .map(key_to_kanata)
+                .collect::<Result<Vec<_>, _>>()?;
+            let keys = config
+                .caps_word_continue()
+                .iter()
+                .map(key_to_kanata)
+                .collect::<Result<Vec<_>, _>>()?;
+            format!(
+                "(caps-word-custom {} ({}) ({}))",
+                timing.caps_word_timeout(),
+                shifted.join(" "),
+                keys.join(" ")
+            )
+        }
+        Action::Repeat => "rpt".into(),
+        Action::Leader => "sldr".into(),
+        Action::AltRepeat => {
+            return Err(Error::from("Kanata has no alt-repeat")
+                .with_code(Code::Unsupported)
+                .with_help("map the keys explicitly, e.g. with tap-dance"));
+        }
+        Action::LayerWithMods(l, mods) => format!(
+            "(multi (layer-while-held {}) {})",
+            l,
+            mods.keys()
+                .map(|k| key_to_kanata(&k))
+                .collect::<Result<Vec<_>, _>>()?
+                .join(" ")
+        ),
+        Action::MouseMove(direction, options) => mouse("movemouse", direction, options),
+        Action::MouseWheel(direction, options) => mouse("mwheel", direction, options),
+        Action::Unicode(c) => {
+            return Err(Error::from(format!("Character {:?} has no key", c))
+                .with_code(Code::Unsupported)
+                .with_help("add it to the keymap of the layer with defkeymap"));
+        }
         Action::Sequence(actions) => format!(
             "(macro {})",
             actions
                 .iter()
-                .map(action_to_kanata)
+                .map(|a| action_to_kanata(a, config))
                 .collect::<Result<Vec<_>, _>>()?
                 .join(" ")
         ),
         Action::Hold(_) | Action::Release(_) => {
-            return Err(format!("Action {:?} not in sequence", action));
+            return Err(Error::from(format!("Action {:?} not in sequence", action))
+                .with_code(Code::Unsupported));
         }
     })
 }
 
-pub fn format_mods(mods: &[&Key]) -> Option<String> {
-    let set: HashSet<_> = mods.iter().copied().map(|k| k.clone()).collect();
-    let res = set
+/// `movemouse-*`, `movemouse-accel-*` or `mwheel-*`, diagonals move along
+/// both axes at once. Acceleration starts from one pixel per interval
+fn mouse(name: &str, direction: &Direction, options: &MouseOptions) -> String {
+    let (interval, speed) = if name == "mwheel" {
+        MOUSE_WHEEL
+    } else {
+        MOUSE_MOVE
+    };
+    let interval = options.interval.unwrap_or(interval);
+    let speed = options.speed.unwrap_or(speed);
+    let actions: Vec<_> = direction
+        .parts()
         .iter()
-        .filter_map(|m| {
-            Some(match m {
-                Key::LeftCtrl => "C",
-                Key::RightCtrl => "RC",
-                Key::LeftShift => "S",
-                Key::RightShift => "RS",
-                Key::LeftAlt => "A",
-                Key::RightAlt => "A",
-                Key::LeftMeta => "M",
-                Key::RightMeta => "RM",
-                _ => return None,
-            })
+        .map(|d| {
+            let d = match d {
+                Direction::Up => "up",
+                Direction::Down => "down",
+                Direction::Left => "left",
+                _ => "right",
+            };
+            match options.accel {
+                Some(accel) => format!("({}-accel-{} {} {} 1 {})", name, d, interval, accel, speed),
+                None => format!("({}-{} {} {})", name, d, interval, speed),
+            }
         })
-        .collect::<Vec<_>>();
-    if res.len() == set.len() {
-        Some(res.join("-"))
-    } else {
-        None
+        .collect();
+    match actions.as_slice() {
+        [action] => action.clone(),
+        _ => format!("(multi {})", actions.join(" ")),
     }
 }
 
-fn key_to_kanata(key: &Key) -> String {
+/// Kanata name of a key, for key positions (`defsrc`, combos, sequences,
+/// overrides) as well as taps
+fn key_to_kanata(key: &Key) -> Result<String, Error> {
     match key {
-        Key::Fn(x) => {
-            if *x == 1 {
-                "fn".into()
-            } else {
-                format!("fn{}", x)
+        Key::Fn(1) => Ok("fn".into()),
+        Key::Fn(x) => Ok(format!("fn{}", x)),
+        key => key.kanata().map(Into::into).ok_or_else(|| {
+            let e = Error::from(format!("Kanata has no key {}", key)).with_code(Code::Unsupported);
+            match key {
+                // The OS reports the non-US # key as backslash
+                Key::NonUsHash => e.with_help("use `\\` instead"),
+                key if mouse_key(key).is_some() => {
+                    e.with_note("kanata moves the mouse with actions, they can't be a key position")
+                }
+                _ => e,
+            }
+        }),
+    }
+}
+
+/// Action of the mouse keys kanata has no key name for
+fn mouse_key(key: &Key) -> Option<String> {
+    let default = MouseOptions::default();
+    Some(match key {
+        Key::MouseCursorUp => mouse("movemouse", &Direction::Up, &default),
+        Key::MouseCursorDown => mouse("movemouse", &Direction::Down, &default),
+        Key::MouseCursorLeft => mouse("movemouse", &Direction::Left, &default),
+        Key::MouseCursorRight => mouse("movemouse", &Direction::Right, &default),
+        // Speed of the mouse keys while held, as the QMK acceleration keys
+        Key::MouseAcceleration0 => "(movemouse-speed 25)".into(),
+        Key::MouseAcceleration1 => "(movemouse-speed 50)".into(),
+        Key::MouseAcceleration2 => "(movemouse-speed 200)".into(),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use s_expression::Span;
+
+    fn kanata(content: &str) -> String {
+        content.parse::<Layout>().unwrap().kanata().unwrap()
+    }
+
+    /// Top-level `(name ...)` form of `config`, forms are separated by a
+    /// blank line
+    fn form<'a>(config: &'a str, name: &str) -> &'a str {
+        let start = config
+            .find(&format!("({}", name))
+            .unwrap_or_else(|| panic!("{} not in\n{}", name, config));
+        let end = config[start..]
+            .find("\n\n")
+            .map_or(config.len(), |i| start + i);
+        &config[start..end]
+    }
+
+    /// Lowered action of `key` in the default layer of `config`
+    fn action<'a>(config: &'a str, key: &str) -> &'a str {
+        config
+            .split("(deflayermap (default)\n")
+            .nth(1)
+            .into_iter()
+            .flat_map(|layer| layer.lines().take_while(|l| *l != ")"))
+            .find_map(|l| l.strip_prefix('\t')?.strip_prefix(key)?.strip_prefix(' '))
+            .unwrap_or_else(|| panic!("{} not in the default layer of\n{}", key, config))
+    }
+
+    #[test]
+    fn defcfg_timing() {
+        let config = kanata(
+            "(defcfg tapping-term 180 (kanata hold-timeout 250 process-unmapped-keys yes))
+            (defsrc a s)
+            (deflayermap default a (tap-hold a M))",
+        );
+        assert_eq!(
+            form(&config, "defcfg"),
+            "(defcfg\n\tprocess-unmapped-keys yes\n)"
+        );
+        assert_eq!(form(&config, "defsrc"), "(defsrc a s)");
+        assert_eq!(action(&config, "a"), "(tap-hold 180 250 a lmeta)");
+    }
+
+    #[test]
+    fn tap_hold_flavors() {
+        let config = kanata(
+            "(defsrc a s d f)
+            (deflayermap default
+                a (tap-hold a M :timeout 300 :flavor press)
+                s (tap-hold s A :tap-timeout 150 :flavor release)
+                d (tap-hold d C :tap-keys (a s)))",
+        );
+        assert_eq!(action(&config, "a"), "(tap-hold-press 200 300 a lmeta)");
+        assert_eq!(action(&config, "s"), "(tap-hold-release 150 200 s lalt)");
+        assert_eq!(
+            action(&config, "d"),
+            "(tap-hold-release-keys 200 200 d lctl (a s))"
+        );
+    }
+
+    #[test]
+    fn home_row_mod_hands() {
+        let config = kanata(
+            "(keyboard imperial44)
+            (defhands right (g))
+            (deflayermap default f (home-row-mod f S :timeout 180) j (home-row-mod j S))",
+        );
+        assert_eq!(
+            action(&config, "f"),
+            "(tap-hold-release-keys 200 180 f lsft (q w e r t tab a s d lsft z x c v b up lctl spc down lalt))"
+        );
+        assert_eq!(
+            action(&config, "j"),
+            "(tap-hold-release-keys 200 200 j lsft (y u i o p bks g h k l ; rmeta pgup n m , . / rsft pgdn enter))"
+        );
+    }
+
+    #[test]
+    fn tap_dance() {
+        let config = kanata(
+            "(defsrc a s d)
+            (deflayermap default
+                a (tap-dance :tap a :double-tap esc :term 150)
+                s (tap-dance-eager :tap s :hold M :tap-hold C)
+                d (tap-dance :tap d :hold A))",
+        );
+        assert_eq!(action(&config, "a"), "(tap-dance 150 (a esc))");
+        assert_eq!(
+            action(&config, "s"),
+            "(tap-dance-eager 200 ((tap-hold 200 200 s lmeta) (tap-hold 200 200 XX lctl)))"
+        );
+        assert_eq!(action(&config, "d"), "(tap-hold 200 200 d lalt)");
+    }
+
+    #[test]
+    fn combos() {
+        let config = kanata(
+            "(defcfg combo-term 40)
+            (defsrc j k l)
+            (deflayer default j k l)
+            (deflayer nav j k l)
+            (deflayer num j k l)
+            (defcombo (j k) esc)
+            (defcombo (k l) (layer-while-held nav) :timeout 30 :layers (default num))",
+        );
+        assert_eq!(
+            form(&config, "defcfg"),
+            "(defcfg\n\tconcurrent-tap-hold yes\n)"
+        );
+        assert_eq!(
+            form(&config, "defchordsv2"),
+            "(defchordsv2\n\t(j k) esc 40 all-released ()\n\t(k l) (layer-while-held nav) 30 all-released (nav)\n)"
+        );
+    }
+
+    #[test]
+    fn one_shot() {
+        let config = kanata(
+            "(defcfg one-shot-timeout 800)
+            (defsrc a s d)
+            (deflayer nav a s d)
+            (deflayermap default
+                a (one-shot S :end release)
+                s (one-shot (layer-while-held nav) :timeout 500)
+                d (one-shot (layer-while-held nav) :pause-on-layer yes))",
+        );
+        assert_eq!(action(&config, "a"), "(one-shot-release 800 lsft)");
+        assert_eq!(
+            action(&config, "s"),
+            "(one-shot-press 500 (layer-while-held nav))"
+        );
+        assert_eq!(
+            action(&config, "d"),
+            "(multi (one-shot-press 800 (layer-while-held nav)) (one-shot-pause-processing 5))"
+        );
+    }
+
+    #[test]
+    fn layer_actions() {
+        let config = kanata(
+            "(defsrc s f)
+            (deflayer nav _ _)
+            (deflayermap default
+                s (layer-to nav)
+                f (layer-with-mods nav C-S))",
+        );
+        assert_eq!(action(&config, "s"), "(layer-switch nav)");
+        assert_eq!(
+            action(&config, "f"),
+            "(multi (layer-while-held nav) lctl lsft)"
+        );
+
+        // kanata's layer-toggle doesn't latch
+        for toggle in ["layer-toggle", "layer-tap-toggle"] {
+            let layout: Layout = format!(
+                "(defsrc a s)
+                (deflayer nav a s)
+                (deflayer default ({} nav) s)",
+                toggle
+            )
+            .parse()
+            .unwrap();
+            let e = layout.kanata().unwrap_err();
+            assert_eq!(e.code, Some(Code::Unsupported));
+            assert_eq!(
+                e.message,
+                format!("Kanata has no latching layer toggle, ({} nav)", toggle)
+            );
+            assert_eq!(e.span, Some(Span::new(74, 81)));
+            assert_eq!(
+                e.help.as_deref(),
+                Some("use (layer-switch nav) with a (layer-switch default) key in nav")
+            );
+        }
+    }
+
+    #[test]
+    fn caps_word() {
+        let config = kanata(
+            "(defsrc a s)
+            (deflayermap default a caps-word s repeat)",
+        );
+        assert_eq!(action(&config, "a"), "(caps-word 5000)");
+        assert_eq!(action(&config, "s"), "rpt");
+
+        let config = kanata(
+            "(defcfg caps-word-timeout 3000 caps-word-keys (- ;))
+            (defsrc a)
+            (deflayermap default a caps-word)",
+        );
+        assert_eq!(
+            action(&config, "a"),
+            "(caps-word-custom 3000 (a b c d e f g h i j k l m n o p q r s t u v w x y z -) (1 2 3 4 5 6 7 8 9 0 bks del ;))"
+        );
+    }
+
+    #[test]
+    fn sequences() {
+        let config = kanata(
+            "(defsrc a)
+            (defalias st \"git status\")
+            (deflayermap default a leader)
+            (defseq (g c) (macro \"git commit\") (g s) @st)",
+        );
+        assert_eq!(action(&config, "a"), "sldr");
+        assert_eq!(
+            form(&config, "defvirtualkeys"),
+            "(defvirtualkeys\n\tseq-0 (macro g i t spc c o m m i t)\n\tseq-1 (macro g i t spc s t a t u s)\n)"
+        );
+        assert_eq!(
+            form(&config, "defseq"),
+            "(defseq\n\tseq-0 (g c)\n\tseq-1 (g s)\n)"
+        );
+    }
+
+    #[test]
+    fn mouse() {
+        let config = kanata(
+            "(defsrc a s d f g)
+            (deflayermap default
+                a (mouse-move up-left :accel 300 :speed 12)
+                s (mouse-move down :speed 4 :interval 5)
+                d (mouse-wheel right)
+                f mcup
+                g MouseAcceleration2)",
+        );
+        assert_eq!(
+            action(&config, "a"),
+            "(multi (movemouse-accel-up 10 300 1 12) (movemouse-accel-left 10 300 1 12))"
+        );
+        assert_eq!(action(&config, "s"), "(movemouse-down 5 4)");
+        assert_eq!(action(&config, "d"), "(mwheel-right 50 120)");
+        assert_eq!(action(&config, "f"), "(movemouse-up 10 8)");
+        assert_eq!(action(&config, "g"), "(movemouse-speed 200)");
+        let e = "(defsrc a mcup)\n(deflayer default a b)"
+            .parse::<Layout>()
+            .unwrap()
+            .kanata()
+            .unwrap_err();
+        assert_eq!(e.message, "Kanata has no key MouseCursorUp");
+        assert_eq!(e.code, Some(Code::Unsupported));
+    }
+
+    #[test]
+    fn media_keys() {
+        let config = kanata(
+            "(defsrc a s d)
+            (deflayermap default a next s BrightnessDown d sleep)",
+        );
+        assert_eq!(action(&config, "a"), "next");
+        assert_eq!(action(&config, "s"), "brdn");
+        assert_eq!(action(&config, "d"), "sleep");
+    }
+
+    #[test]
+    fn every_key() {
+        let config = Config::default();
+        for key in Key::ALL {
+            match action_to_kanata(&Action::Tap(*key), &config) {
+                Ok(tap) => assert!(!tap.is_empty(), "{:?}", key),
+                Err(e) => assert!(key.kanata().is_none() && e.code == Some(Code::Unsupported)),
             }
         }
-        Key::F13 => "f13".into(),
-        Key::F14 => "f14".into(),
-        Key::F15 => "f15".into(),
-        Key::F16 => "f16".into(),
-        Key::F17 => "f17".into(),
-        Key::F18 => "f18".into(),
-        Key::F19 => "f19".into(),
-        Key::F20 => "f20".into(),
-        Key::F21 => "f21".into(),
-        Key::F22 => "f22".into(),
-        Key::F23 => "f23".into(),
-        Key::F24 => "f24".into(),
-        Key::VolumeUp => "volu".into(),
-        Key::VolumeDown => "vold".into(),
-        Key::VolumeMute => "mute".into(),
-        Key::Esc => "esc".into(),
-        Key::F1 => "f1".into(),
-        Key::F2 => "f2".into(),
-        Key::F3 => "f3".into(),
-        Key::F4 => "f4".into(),
-        Key::F5 => "f5".into(),
-        Key::F6 => "f6".into(),
-        Key::F7 => "f7".into(),
-        Key::F8 => "f8".into(),
-        Key::F9 => "f9".into(),
-        Key::F10 => "f10".into(),
-        Key::F11 => "f11".into(),
-        Key::F12 => "f12".into(),
-        Key::PrintScreen => "prnt".into(),
-        Key::ScrollLock => "sclk".into(),
-        Key::Pause => "pause".into(),
-        Key::Grave => "grv".into(),
-
-        Key::Zero => "0".into(),
-        Key::One => "1".into(),
-        Key::Two => "2".into(),
-        Key::Three => "3".into(),
-        Key::Four => "4".into(),
-        Key::Five => "5".into(),
-        Key::Six => "6".into(),
-        Key::Seven => "7".into(),
-        Key::Eight => "8".into(),
-        Key::Nine => "9".into(),
-
-        Key::Minus => "-".into(),
-        Key::Equal => "=".into(),
-        Key::Backspace => "bks".into(),
-        Key::Insert => "ins".into(),
-        Key::Home => "home".into(),
-        Key::PageUp => "pgup".into(),
-        Key::PageDown => "pgdn".into(),
-        Key::Numlock => "nlck".into(),
-        Key::Tab => "tab".into(),
-        Key::LeftBracket => "[".into(),
-        Key::RightBracket => "]".into(),
-        Key::Backslash => "\\".into(),
-        Key::Delete => "del".into(),
-        Key::End => "end".into(),
-        Key::CapsLock => "caps".into(),
-        Key::Q => "q".into(),
-        Key::W => "w".into(),
-        Key::E => "e".into(),
-        Key::R => "r".into(),
-        Key::T => "t".into(),
-        Key::Y => "y".into(),
-        Key::U => "u".into(),
-        Key::I => "i".into(),
-        Key::O => "o".into(),
-        Key::P => "p".into(),
-        Key::A => "a".into(),
-        Key::S => "s".into(),
-        Key::D => "d".into(),
-        Key::F => "f".into(),
-        Key::G => "g".into(),
-        Key::H => "h".into(),
-        Key::J => "j".into(),
-        Key::K => "k".into(),
-        Key::L => "l".into(),
-        Key::Z => "z".into(),
-        Key::X => "x".into(),
-        Key::C => "c".into(),
-        Key::V => "v".into(),
-        Key::B => "b".into(),
-        Key::N => "n".into(),
-        Key::M => "m".into(),
-        Key::Enter => "enter".into(),
-
-        Key::LeftShift => "lsft".into(),
-        Key::RightShift => "rsft".into(),
-        Key::LeftCtrl => "lctl".into(),
-        Key::RightCtrl => "rctl".into(),
-        Key::LeftMeta => "lmeta".into(),
-        Key::RightMeta => "rmeta".into(),
-        Key::LeftAlt => "lalt".into(),
-        Key::RightAlt => "ralt".into(),
-
-        Key::Comma => ",".into(),
-        Key::Dot => ".".into(),
-        Key::Slash => "/".into(),
-        Key::Space => "spc".into(),
-        Key::Menu => "menu".into(),
-        Key::Semicolon => ";".into(),
-        Key::Apostrophe => "'".into(),
-
-        Key::Up => "up".into(),
-        Key::Down => "down".into(),
-        Key::Left => "left".into(),
-        Key::Right => "right".into(),
-
-        Key::Kp0 => "kp0".into(),
-        Key::Kp1 => "kp1".into(),
-        Key::Kp2 => "kp2".into(),
-        Key::Kp3 => "kp3".into(),
-        Key::Kp4 => "kp4".into(),
-        Key::Kp5 => "kp5".into(),
-        Key::Kp6 => "kp6".into(),
-        Key::Kp7 => "kp7".into(),
-        Key::Kp8 => "kp8".into(),
-        Key::Kp9 => "kp9".into(),
-        Key::KpDot => "kp.".into(),
-        Key::KpEnter => "kprt".into(),
-        Key::KpPlus => "kp+".into(),
-        Key::KpSlash => "kp/".into(),
-        Key::KpAsterisk => "kp*".into(),
-        Key::KpMinus => "kp-".into(),
-        Key::KpEqual => "kp=".into(),
-
-        Key::MediaPlayPause => "pp".into(),
-        Key::MouseCursorUp => todo!(),
-        Key::MouseCursorDown => todo!(),
-        Key::MouseCursorLeft => todo!(),
-        Key::MouseCursorRight => todo!(),
-        Key::MouseWheelUp => "mwu".into(),
-        Key::MouseWheelDown => "mwd".into(),
-        Key::MouseWheelLeft => "mwl".into(),
-        Key::MouseWheelRight => "mwr".into(),
-        Key::MouseButton1 => "mlft".into(),
-        Key::MouseButton2 => "mrgt".into(),
-        Key::MouseButton3 => "mmid".into(),
-        Key::MouseButton4 => "mbck".into(),
-        Key::MouseButton5 => "mfwd".into(),
-        Key::MouseAcceleration0 => todo!(),
-        Key::MouseAcceleration1 => todo!(),
-        Key::MouseAcceleration2 => todo!(),
+        let e = action_to_kanata(&Action::Tap(Key::BrowserStop), &config).unwrap_err();
+        assert_eq!(e.message, "Kanata has no key BrowserStop");
+        let e = action_to_kanata(&Action::Tap(Key::NonUsHash), &config).unwrap_err();
+        assert_eq!(e.help.as_deref(), Some("use `\\` instead"));
     }
 }
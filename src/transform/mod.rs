@@ -1,4 +1,3 @@
-mod config;
 mod graph;
 mod kanata;
 mod vial;
@@ -23,8 +23,8 @@ impl std::fmt::Debug for TapDance {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "tap-dance({:?}, {:?}, {:?}, {:?})",
-            self.tap, self.hold, self.double_tap, self.tap_hold,
+            "tap-dance({:?}, {:?}, {:?}, {:?}, {}ms)",
+            self.tap, self.hold, self.double_tap, self.tap_hold, self.tapping_term,
         )
     }
 }
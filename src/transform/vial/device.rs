@@ -39,12 +39,12 @@ pub fn get_device(
 }
 
 pub fn unlock_device(dev: &HidDevice, meta: &Value, unlock: bool) -> Result<(), String> {
-    let mut status = protocol::get_locked_status(&dev).map_err(|e| e.to_string())?;
+    let mut status = protocol::get_locked_status(dev).map_err(|e| e.to_string())?;
     if status.locked && unlock {
         println!("Starting unlock process... ");
         println!("Push marked buttons and keep then pushed to unlock...");
         let layout_options = &meta["layouts"]["labels"];
-        let state = protocol::load_layout_options(&dev).map_err(|e| e.to_string())?;
+        let state = protocol::load_layout_options(dev).map_err(|e| e.to_string())?;
         let options =
             protocol::LayoutOptions::from_json(state, layout_options).map_err(|e| e.to_string())?;
         let mut buttons = vitaly::keymap::keymap_to_buttons(&meta["layouts"]["keymap"], &options)
@@ -65,26 +65,26 @@ pub fn unlock_device(dev: &HidDevice, meta: &Value, unlock: bool) -> Result<(),
         }
         vitaly::keymap::render_and_dump(&buttons, Some(button_labels));
         if !status.unlock_in_progress {
-            protocol::start_unlock(&dev).map_err(|e| e.to_string())?;
+            protocol::start_unlock(dev).map_err(|e| e.to_string())?;
         }
         let sleep_duration = std::time::Duration::from_millis(100);
         let mut unlocked = false;
         let mut polls_remaining: u8;
         while !unlocked {
             std::thread::sleep(sleep_duration);
-            (unlocked, polls_remaining) = protocol::unlock_poll(&dev).map_err(|e| e.to_string())?;
+            (unlocked, polls_remaining) = protocol::unlock_poll(dev).map_err(|e| e.to_string())?;
             print!(
                 "Seconds remaining: {:.1} keep pushing...\r",
                 (polls_remaining as f64) / 10.0
             );
             std::io::stdout().flush().map_err(|e| e.to_string())?;
         }
-        status = protocol::get_locked_status(&dev).map_err(|e| e.to_string())?;
+        status = protocol::get_locked_status(dev).map_err(|e| e.to_string())?;
         println!("\nDevice is locked: {}", status.locked);
     } else if !status.locked {
         println!("Locking keyboard...");
-        protocol::set_locked(&dev).map_err(|e| e.to_string())?;
-        status = protocol::get_locked_status(&dev).map_err(|e| e.to_string())?;
+        protocol::set_locked(dev).map_err(|e| e.to_string())?;
+        status = protocol::get_locked_status(dev).map_err(|e| e.to_string())?;
         println!("Device is locked: {}", status.locked);
     }
 
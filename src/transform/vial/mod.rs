@@ -1,5 +1,5 @@
 mod keycode;
-use keycode::{Keycode, format_mods, key_to_mod, key_to_string};
+use keycode::{Keycode, format_mods, mod_names};
 mod actions;
 mod device;
 mod overrides;
@@ -7,21 +7,25 @@ use log::warn;
 use overrides::Override;
 
 use super::graph::{Node, priority_topo_sort};
-use crate::layout::{Action, Layer, Layout};
+use crate::layout::{Action, Combo, Direction, Flavor, Layer, Layout, MouseOptions, Timing};
 use actions::{Macro, MacroAction, TapDance, VialAction};
 use device::{get_device, unlock_device};
 use hidapi::HidApi;
-use keys::keys::{Key, KeyIndex};
+use keys::{
+    keys::{Key, KeyIndex},
+    mods::Mods,
+};
 use parser::VialItem;
+use s_expression::{Code, Error, Severity};
 use std::{collections::HashMap, ops::Deref};
 use vitaly::protocol;
 impl Layout {
-    fn sorted_layers(&self) -> Result<Vec<&Layer>, String> {
+    fn sorted_layers(&self) -> Result<Vec<&Layer>, Error> {
         let mut order = priority_topo_sort(
             &self
                 .layers
-                .iter()
-                .map(|(_, l)| {
+                .values()
+                .map(|l| {
                     (
                         l.name.as_str(),
                         Node {
@@ -35,10 +39,29 @@ impl Layout {
         order.reverse();
         order
             .into_iter()
-            .map(|n| self.layers.get(n).ok_or(format!("Layer {:?} not found", n)))
+            .map(|n| {
+                self.layers
+                    .get(n)
+                    .ok_or(format!("Layer {:?} not found", n).into())
+            })
             .collect()
     }
-    pub fn vial(&self, device_id: Option<u16>) -> Result<(), String> {
+    /// Flashes the layout, refuses layouts with trap layers unless `force`
+    pub fn vial(&self, device_id: Option<u16>, force: bool) -> Result<(), Error> {
+        if let Some(trap) = self
+            .warnings
+            .iter()
+            .find(|w| w.code == Some(Code::LayerTrap))
+            && !force
+        {
+            return Err(Error {
+                severity: Severity::Error,
+                ..trap.clone()
+            }
+            .with_note(
+                "refusing to flash a layout that can get stuck, use --force to flash anyway",
+            ));
+        }
         let vial_items = self
             .keyboard
             .vial
@@ -52,12 +75,42 @@ impl Layout {
             .map(|(i, l)| (l.name.as_str(), i))
             .collect();
 
+        if let Some(sequence) = self.sequences.first() {
+            return Err(
+                Error::new(sequence.span, "Vial can't express key sequences")
+                    .with_code(Code::Unsupported)
+                    .with_note("the QMK leader key is configured at compile time")
+                    .with_help("use tap-dance or defcombo for Vial keyboards"),
+            );
+        }
+        let timing = self.config.vial();
+        if timing.caps_word_timeout.is_some() || !self.config.caps_word_keys.is_empty() {
+            warn!("Vial uses the caps word settings of the firmware");
+        }
+        // Tap dances and tap-holds built as tap dances carry their own term,
+        // everything else uses the QMK settings of the firmware
+        if timing.tapping_term.is_some() {
+            warn!(
+                "Vial uses tapping-term for tap dances only, mod-tap and layer-tap keys use the firmware setting"
+            );
+        }
+        let ignored = [
+            ("quick-tap", timing.quick_tap),
+            ("tap-timeout", timing.tap_timeout),
+            ("hold-timeout", timing.hold_timeout),
+            ("combo-term", timing.combo_term),
+            ("one-shot-timeout", timing.one_shot_timeout),
+        ];
+        for (name, _) in ignored.iter().filter(|(_, value)| value.is_some()) {
+            warn!("Vial ignores {}, the firmware setting is used", name);
+        }
         let mut vial = Vial {
             layers: &layers_by_name,
             macros: Default::default(),
             tap_dances: Default::default(),
             overrides: Default::default(),
             version: 6,
+            timing,
         };
 
         let mut layers: Vec<_> = sorted
@@ -67,28 +120,49 @@ impl Layout {
                     .keys
                     .iter()
                     .map(|(key_index, action)| {
-                        let action = vial.action_to_keycode(action)?;
-                        Ok::<_, String>((key_index, action))
+                        let action = vial
+                            .action_to_keycode(action)
+                            .map_err(|e| layer.locate(e))?;
+                        Ok::<_, Error>((key_index, action))
                     })
                     .collect::<Result<_, _>>()?;
                 let layer_index = layers_by_name
                     .get(layer.name.as_str())
-                    .ok_or(format!("Layer {:?} not found", layer.name))?;
+                    .ok_or(Error::from(format!("Layer {:?} not found", layer.name)))?;
 
                 layer.overrides.iter().for_each(|o| {
                     _ = vial
                         .add_override(*layer_index, o)
-                        .map_err(|e| warn!("{}", e.to_string()));
+                        .map_err(|e| warn!("{}", e));
                 });
-                Ok::<_, String>((*layer_index, keys))
+                Ok::<_, Error>((*layer_index, keys))
             })
             .collect::<Result<_, _>>()?;
 
         layers.sort_by_key(|(n, _)| *n);
 
+        let base = layers
+            .first()
+            .map(|(_, keys)| keys.clone())
+            .unwrap_or_default();
+        let combos: Vec<_> = self
+            .combos
+            .iter()
+            .enumerate()
+            .map(|(i, combo)| vial.combo(i, combo, &self.keyboard.source, &base))
+            .collect::<Result<_, _>>()?;
+
         let Some((device, capabilities, meta)) = get_device(&api, device_id) else {
-            return Err("Device not found".to_string());
+            return Err("Device not found".into());
         };
+        if combos.len() > capabilities.combo_count as usize {
+            return Err(Error::from(format!(
+                "{} combos defined, the keyboard supports {}",
+                combos.len(),
+                capabilities.combo_count
+            ))
+            .with_code(Code::Unsupported));
+        }
         let mut macros: Vec<_> = vial.macros.iter().collect();
         macros.sort_by_key(|(_, i)| *i);
         let macros: Vec<_> = macros
@@ -127,7 +201,7 @@ impl Layout {
             .iter()
             .enumerate()
             .map(|(i, (o, l))| o.to_key_override(*l, i))
-            .collect::<Result<_, _>>()?;
+            .collect();
 
         if capabilities.vial_version > 0 {
             unlock_device(&device, &meta, false)?;
@@ -140,7 +214,7 @@ impl Layout {
                 for (k, a) in keys.iter_mut() {
                     match vial_items
                         .get(k)
-                        .ok_or(format!("Vial for {:?} not defined", k))?
+                        .ok_or(Error::from(format!("Vial for {:?} not defined", k)))?
                     {
                         VialItem::KeyCode(row, col) => {
                             protocol::set_keycode(&device, layer_index as u8, *row, *col, a.0)
@@ -159,7 +233,7 @@ impl Layout {
                 if let Some(layer) = sorted.get(layer_index) {
                     println!("Layer {}", layer.name);
                 }
-                Ok::<_, String>(())
+                Ok::<_, Error>(())
             })?;
 
         protocol::set_macros(&device, &capabilities, &macros).map_err(|e| e.to_string())?;
@@ -171,6 +245,12 @@ impl Layout {
             .map_err(|e| e.to_string())?;
         println!("Tap dance");
 
+        combos
+            .iter()
+            .try_for_each(|combo| protocol::set_combo(&device, combo))
+            .map_err(|e| e.to_string())?;
+        println!("Combos");
+
         key_overrides
             .iter()
             .try_for_each(|o| protocol::set_key_override(&device, o))
@@ -190,27 +270,23 @@ struct Vial<'a> {
     layers: &'a HashMap<&'a str, usize>,
     overrides: HashMap<Override, u16>,
     version: u32,
+    timing: Timing,
 }
 impl<'a> Vial<'a> {
-    fn layer_by_name(&self, name: &str) -> Option<usize> {
-        self.layers.get(name).map(|x| *x)
+    fn layer_by_name(&self, name: &str) -> Result<usize, Error> {
+        self.layers.get(name).copied().ok_or_else(|| {
+            Error::from(format!("Layer {} not found", name))
+                .with_code(Code::UnknownLayer)
+                .with_suggestion(name, self.layers.keys().copied())
+        })
     }
-    pub fn add_override(
-        &mut self,
-        layer: usize,
-        o: &crate::layout::Override,
-    ) -> Result<(), String> {
-        let (target, target_mods): (Keycode, Vec<Key>) = match &o.action {
-            Action::Tap(key) => (Keycode::from_key(key, self.version)?, vec![]),
-            Action::NoAction => (Keycode(0), vec![]),
-            Action::TapHold(action, _) => match action.deref() {
-                Action::Tap(key) => (Keycode::from_key(key, self.version)?, vec![]),
-                _ => {
-                    return Err(format!(
-                        "Action {:?} is not supported in override",
-                        o.action
-                    ));
-                }
+    pub fn add_override(&mut self, layer: usize, o: &crate::layout::Override) -> Result<(), Error> {
+        let (target, target_mods): (Keycode, Mods) = match &o.action {
+            Action::Tap(key) => (Keycode::from_key(key, self.version)?, Mods::NONE),
+            Action::NoAction => (Keycode(0), Mods::NONE),
+            Action::TapHold(action, _, _) => match action.deref() {
+                Action::Tap(key) => (Keycode::from_key(key, self.version)?, Mods::NONE),
+                _ => return Err(unsupported_override(&o.action)),
             },
             Action::Multi(elems) => {
                 let taps: Vec<_> = elems
@@ -223,107 +299,167 @@ impl<'a> Vial<'a> {
                         }
                     })
                     .collect();
-                let err = Err(format!(
-                    "Action {:?} is not supported in override",
-                    o.action
-                ));
+                let err = Err(unsupported_override(&o.action));
                 if taps.len() != elems.len() {
                     return err;
                 }
-                let (mods, keys): (Vec<Key>, Vec<Key>) =
+                let (mods, keys): (Vec<&Key>, Vec<&Key>) =
                     taps.into_iter().partition(|k| k.is_modifier());
-                let [tap] = keys.as_slice() else {
+                let ([tap], Ok(mods)) = (keys.as_slice(), Mods::from_keys(mods)) else {
                     return err;
                 };
                 (Keycode::from_key(tap, self.version)?, mods)
             }
-            _ => {
-                return Err(format!(
-                    "Action {:?} is not supported in override",
-                    o.action
-                ));
-            }
+            _ => return Err(unsupported_override(&o.action)),
         };
         let o = Override {
             source: Keycode::from_key(&o.key, self.version)?,
-            target: target,
-            source_mods: o.mods.clone(),
-            target_mods: target_mods,
+            target,
+            source_mods: o.mods,
+            target_mods,
         };
         let entry = self.overrides.entry(o).or_insert(0);
         *entry |= 1 << layer;
         Ok(())
     }
 
-    pub fn action_to_vial(&mut self, action: &Action) -> Result<VialAction, String> {
+    pub fn action_to_vial(&mut self, action: &Action) -> Result<VialAction, Error> {
         Ok(VialAction::Keycode(match action {
             Action::NoAction => Keycode(0),
             Action::Tap(k) => Keycode::from_key(k, self.version)?,
-            Action::TapHold(tap, hold) => {
-                if let Action::Tap(tap) = tap.as_ref() {
-                    match hold.as_ref() {
-                        Action::Tap(k) if k.is_modifier() => {
-                            return Keycode::from_name(
-                                format!(
-                                    "{}_T({})",
-                                    key_to_mod(k).ok_or(format!("Unreachable {:?}", k))?,
-                                    key_to_string(tap)
-                                ),
-                                self.version,
-                            )
-                            .map(VialAction::Keycode);
-                        }
-                        Action::LayerSwitch(x) | Action::LayerWhileHeld(x) => {
-                            let l = self
-                                .layer_by_name(x)
-                                .ok_or(format!("Layer {} not found", x))?;
-                            return Keycode::from_name(
-                                format!("LT({},{})", l, key_to_string(tap)),
-                                self.version,
-                            )
-                            .map(VialAction::Keycode);
-                        }
-                        Action::Multi(actions) => {
-                            let mods: Vec<_> = actions
-                                .iter()
-                                .filter_map(|a| match a {
-                                    Action::Tap(k) => key_to_mod(k),
-                                    _ => None,
-                                })
-                                .collect();
-                            if mods.len() == actions.len() {
-                                return Keycode::from_name(
-                                    format!("MT({},{})", mods.join("|"), key_to_string(tap)),
-                                    self.version,
-                                )
-                                .map(VialAction::Keycode);
-                            }
-                        }
-                        _ => {}
+            Action::TapHold(tap, hold, options) => {
+                if options.flavor != Flavor::Timeout || !options.tap_keys.is_empty() {
+                    warn!(
+                        "Vial has no per-key hold decision, {:?} uses the keyboard setting",
+                        action
+                    );
+                }
+                // Mod-tap and layer-tap keycodes use the tapping term of the keyboard
+                if let (Action::Tap(tap), None) = (tap.as_ref(), options.timeout) {
+                    if let Some(mods) = action_mods(hold).filter(|m| !m.is_empty()) {
+                        let name = match mod_names(mods).as_slice() {
+                            [name] => format!("{}_T({})", name, tap.qmk()),
+                            names => format!("MT({},{})", names.join("|"), tap.qmk()),
+                        };
+                        return Keycode::from_name(name, self.version).map(VialAction::Keycode);
+                    }
+                    if let Action::LayerSwitch(x) | Action::LayerWhileHeld(x) = hold.as_ref() {
+                        let l = self.layer_by_name(x)?;
+                        return Keycode::from_name(
+                            format!("LT({},{})", l, tap.qmk()),
+                            self.version,
+                        )
+                        .map(VialAction::Keycode);
                     }
                 }
 
                 return Ok(VialAction::tap_hold(
                     self.action_to_keycode(tap)?,
                     self.action_to_keycode(hold)?,
+                    options.timeout.unwrap_or(self.timing.tapping_term()),
                 ));
             }
+            Action::OneShot(target, options) => {
+                if options.pause_on_layer {
+                    return Err(Error::from(format!(
+                        "One-shot {} is not supported by Vial",
+                        target
+                    ))
+                    .with_code(Code::Unsupported)
+                    .with_note("QMK one-shots have no :pause-on-layer"));
+                }
+                if options.timeout.is_some() || options.on_release {
+                    warn!("Vial one-shots use the keyboard settings, {}", action);
+                }
+                if let Action::LayerWhileHeld(x) = target.as_ref() {
+                    let layer = self.layer_by_name(x)?;
+                    return Keycode::from_name(format!("OSL({})", layer), self.version)
+                        .map(VialAction::Keycode);
+                }
+                let mods = action_mods(target).ok_or_else(|| {
+                    Error::from(format!("One-shot {} is not supported by Vial", target))
+                        .with_code(Code::Unsupported)
+                })?;
+                Keycode::from_name(format!("OSM({})", qmk_mods(mods)), self.version)?
+            }
+            Action::TapDance(dance) => {
+                if dance.eager {
+                    warn!(
+                        "Vial has no eager tap dances, {:?} waits for the term",
+                        action
+                    );
+                }
+                let mut keycode = |branch: &Option<Box<Action>>| match branch {
+                    Some(action) => self.action_to_keycode(action),
+                    None => Ok(Keycode(0)),
+                };
+                return Ok(VialAction::TapDance(TapDance {
+                    tap: keycode(&dance.tap)?,
+                    hold: keycode(&dance.hold)?,
+                    double_tap: keycode(&dance.double_tap)?,
+                    tap_hold: keycode(&dance.tap_hold)?,
+                    tapping_term: dance.term.unwrap_or(self.timing.tapping_term()),
+                }));
+            }
             Action::Alias(_) | Action::Unicode(_) => {
-                return Err(format!("Action {:?} not implemented", action));
+                return Err(Error::from(format!("Action {:?} not implemented", action))
+                    .with_code(Code::Unsupported));
             }
             Action::LayerSwitch(x) => {
-                let layer = self
-                    .layer_by_name(x)
-                    .ok_or(format!("Layer {} not found", x))?;
+                let layer = self.layer_by_name(x)?;
                 Keycode::from_name(format!("DF({})", layer), self.version)?
             }
             Action::LayerWhileHeld(x) => {
-                let layer = self
-                    .layer_by_name(x)
-                    .ok_or(format!("Layer {} not found", x))?;
+                let layer = self.layer_by_name(x)?;
 
                 Keycode::from_name(format!("MO({})", layer), self.version)?
             }
+            Action::LayerToggle(x) | Action::LayerTo(x) | Action::LayerTapToggle(x) => {
+                let layer = self.layer_by_name(x)?;
+                let name = match action {
+                    Action::LayerToggle(_) => "TG",
+                    Action::LayerTo(_) => "TO",
+                    _ => "TT",
+                };
+                Keycode::from_name(format!("{}({})", name, layer), self.version)?
+            }
+            Action::Leader => {
+                return Err(Error::from("Vial has no leader key")
+                    .with_code(Code::Unsupported)
+                    .with_note("the QMK leader key is configured at compile time"));
+            }
+            Action::MouseMove(direction, options) | Action::MouseWheel(direction, options) => {
+                if *options != MouseOptions::default() {
+                    warn!("Vial mouse keys use the firmware settings, {:?}", action);
+                }
+                let wheel = matches!(action, Action::MouseWheel(..));
+                let key = match (direction, wheel) {
+                    (Direction::Up, false) => Key::MouseCursorUp,
+                    (Direction::Down, false) => Key::MouseCursorDown,
+                    (Direction::Left, false) => Key::MouseCursorLeft,
+                    (Direction::Right, false) => Key::MouseCursorRight,
+                    (Direction::Up, true) => Key::MouseWheelUp,
+                    (Direction::Down, true) => Key::MouseWheelDown,
+                    (Direction::Left, true) => Key::MouseWheelLeft,
+                    (Direction::Right, true) => Key::MouseWheelRight,
+                    _ => {
+                        return Err(Error::from(format!(
+                            "Vial has no diagonal mouse keys, {}",
+                            action
+                        ))
+                        .with_code(Code::Unsupported)
+                        .with_help("press two mouse keys together"));
+                    }
+                };
+                Keycode::from_key(&key, self.version)?
+            }
+            Action::CapsWord => Keycode::from_name("QK_CAPS_WORD_TOGGLE".into(), self.version)?,
+            Action::Repeat => Keycode::from_name("QK_REPEAT_KEY".into(), self.version)?,
+            Action::AltRepeat => Keycode::from_name("QK_ALT_REPEAT_KEY".into(), self.version)?,
+            Action::LayerWithMods(x, mods) => {
+                let layer = self.layer_by_name(x)?;
+                Keycode::from_name(format!("LM({},{})", layer, qmk_mods(*mods)), self.version)?
+            }
             Action::Multi(elems) => {
                 let taps: Vec<_> = elems
                     .iter()
@@ -337,21 +473,21 @@ impl<'a> Vial<'a> {
                     .collect();
                 if taps.len() == elems.len() {
                     let (mods, keys): (Vec<&Key>, Vec<&Key>) =
-                        taps.iter().partition(|k| k.is_modifier());
-                    if let [tap] = keys.as_slice() {
-                        if let Some(mods) = format_mods(mods.as_slice()) {
-                            return Keycode::from_name(
-                                format!("{}({})", mods, key_to_string(tap)),
-                                self.version,
-                            )
-                            .map(VialAction::Keycode);
-                        }
+                        taps.into_iter().partition(|k| k.is_modifier());
+                    if let ([tap], Ok(mods)) = (keys.as_slice(), Mods::from_keys(mods))
+                        && let Some(mods) = format_mods(mods)
+                    {
+                        return Keycode::from_name(
+                            format!("{}({})", mods, tap.qmk()),
+                            self.version,
+                        )
+                        .map(VialAction::Keycode);
                     }
                 }
                 let actions: Vec<_> = elems
                     .iter()
                     .map(|a| self.action_to_keycode(a).map(MacroAction::Tap))
-                    .collect::<Result<_, String>>()?;
+                    .collect::<Result<_, Error>>()?;
 
                 return Ok(VialAction::Macro(Macro(actions)));
             }
@@ -370,7 +506,7 @@ impl<'a> Vial<'a> {
                             a => MacroAction::Tap(self.action_to_keycode(a)?),
                         })
                     })
-                    .collect::<Result<_, String>>()?;
+                    .collect::<Result<_, Error>>()?;
 
                 let result = act.iter().skip(1).fold(vec![act[0].clone()], |mut acc, x| {
                     if acc
@@ -386,11 +522,56 @@ impl<'a> Vial<'a> {
                 return Ok(VialAction::Macro(Macro(result)));
             }
             Action::Hold(_) | Action::Release(_) => {
-                return Err(format!("Action {:?} not in sequence", action));
+                return Err(Error::from(format!("Action {:?} not in sequence", action))
+                    .with_code(Code::Unsupported));
             }
         }))
     }
 
+    /// Combo triggered by the keycodes of its keys on the base layer
+    fn combo(
+        &mut self,
+        index: usize,
+        combo: &Combo,
+        source: &HashMap<Key, KeyIndex>,
+        base: &HashMap<&KeyIndex, Keycode>,
+    ) -> Result<protocol::Combo, Error> {
+        if combo.keys.len() > 4 {
+            return Err(Error::from(format!(
+                "Combo {:?} has {} keys, Vial supports 4",
+                combo.keys,
+                combo.keys.len()
+            ))
+            .with_code(Code::Unsupported));
+        }
+        if combo.timeout.is_some() || !combo.layers.is_empty() {
+            warn!(
+                "Vial combos use the keyboard combo term and work on every layer, {:?}",
+                combo.keys
+            );
+        }
+        let mut keys = combo
+            .keys
+            .iter()
+            .map(|k| {
+                source
+                    .get(k)
+                    .and_then(|i| base.get(i))
+                    .map(|keycode| keycode.0)
+                    .ok_or_else(|| Error::from(format!("Key {:?} not found", k)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        keys.resize(4, 0);
+        Ok(protocol::Combo {
+            index: index as u8,
+            key1: keys[0],
+            key2: keys[1],
+            key3: keys[2],
+            key4: keys[3],
+            output: self.action_to_keycode(&combo.action)?.0,
+        })
+    }
+
     fn tap_dance(&mut self, td: TapDance) -> u8 {
         if let Some(i) = self.tap_dances.get(&td) {
             *i
@@ -410,7 +591,7 @@ impl<'a> Vial<'a> {
         }
     }
 
-    fn action_to_keycode(self: &mut Vial<'a>, action: &Action) -> Result<Keycode, String> {
+    fn action_to_keycode(self: &mut Vial<'a>, action: &Action) -> Result<Keycode, Error> {
         Ok(match self.action_to_vial(action)? {
             VialAction::Keycode(keycode) => keycode,
             VialAction::TapDance(td) => {
@@ -422,3 +603,29 @@ impl<'a> Vial<'a> {
         })
     }
 }
+
+/// Modifiers of a tap or a multi of taps, `None` for any other action
+fn action_mods(action: &Action) -> Option<Mods> {
+    match action {
+        Action::Tap(k) => Mods::from_key(k),
+        Action::Multi(actions) => actions
+            .iter()
+            .try_fold(Mods::NONE, |acc, a| action_mods(a).map(|m| acc | m)),
+        _ => None,
+    }
+}
+
+/// Modifiers as `MOD_LCTL|MOD_LSFT` for `OSM` and `LM`
+fn qmk_mods(mods: Mods) -> String {
+    mod_names(mods)
+        .iter()
+        .map(|m| format!("MOD_{}", m))
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+fn unsupported_override(action: &Action) -> Error {
+    Error::from(format!("Action {:?} is not supported in override", action))
+        .with_code(Code::Unsupported)
+        .with_note("Vial overrides can only send a key with modifiers")
+}